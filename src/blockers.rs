@@ -0,0 +1,255 @@
+//! How much probability of the wanted gizmo is lost to the other perks of a material combination
+use crate::{
+    gizmo_cost_thresholds::find_gizmo_cost_thresholds,
+    jagex_sort,
+    perk_values::{calc_perk_rank_probabilities, get_perk_values, permutate_perk_ranks},
+    prelude::*,
+    result::format_float,
+};
+use colored::Colorize;
+use itertools::Itertools;
+use std::fmt;
+
+/// A perk that isn't wanted, with the probability of the wanted gizmo that is lost because it can be generated
+pub struct Blocker {
+    pub perk: PerkName,
+    /// The materials of the combination that give the perk
+    pub materials: Vec<MaterialName>,
+    /// Probability gained when the perk is never generated
+    pub lost: f64,
+    /// Probability gained when only this rank is never generated, for each rank
+    pub ranks: Vec<(u8, f64)>,
+}
+
+pub struct BlockerReport {
+    pub wanted: Gizmo,
+    pub any_second: bool,
+    /// Probability of the wanted gizmo
+    pub probability: f64,
+    /// Most lost probability first
+    pub blockers: Vec<Blocker>,
+}
+
+/// Compares the probability of the wanted gizmo with the probability when a rank of another perk is replaced by rank 0,
+/// as if the perk was never generated.
+pub fn find_blockers(
+    data: &Data,
+    budget: &Budget,
+    input_materials: &Vec<MaterialName>,
+    gizmo_type: GizmoType,
+    is_ancient: bool,
+    wanted: Gizmo,
+    any_second: bool,
+) -> BlockerReport {
+    let perk_values = get_perk_values(data, input_materials, gizmo_type, is_ancient);
+    let perk_values = calc_perk_rank_probabilities(data, &perk_values, is_ancient);
+    let probability = wanted_probability(&perk_values, budget, &wanted, any_second);
+
+    let mut blockers = vec![];
+    for (i, perk) in perk_values.iter().enumerate() {
+        if perk.name == wanted.perks.0.name || perk.name == wanted.perks.1.name {
+            continue;
+        }
+        let non_zero_ranks = (perk.i_first.max(1)..=perk.i_last)
+            .filter(|x| perk.ranks[*x].probability > 0.0)
+            .collect_vec();
+        if non_zero_ranks.is_empty() {
+            continue;
+        }
+
+        let lost = |ranks: &[usize]| {
+            let without = without_ranks(&perk_values, i, ranks);
+            wanted_probability(&without, budget, &wanted, any_second) - probability
+        };
+        let ranks = non_zero_ranks
+            .iter()
+            .map(|x| (perk.ranks[*x].values.rank, lost(&[*x])))
+            .collect_vec();
+
+        blockers.push(Blocker {
+            perk: perk.name,
+            materials: input_materials
+                .iter()
+                .copied()
+                .unique()
+                .filter(|x| gives_perk(data, *x, gizmo_type, is_ancient, perk.name))
+                .collect(),
+            lost: lost(&non_zero_ranks),
+            ranks,
+        });
+    }
+    blockers.sort_by(|x, y| f64::total_cmp(&y.lost, &x.lost));
+
+    BlockerReport {
+        wanted,
+        any_second,
+        probability,
+        blockers,
+    }
+}
+
+fn wanted_probability(
+    perk_values: &PerkValuesVec,
+    budget: &Budget,
+    wanted: &Gizmo,
+    any_second: bool,
+) -> f64 {
+    let mut probability = 0.0;
+    for mut comb in permutate_perk_ranks(perk_values, None) {
+        jagex_sort::jagex_quicksort(&mut comb);
+        let mut cost_thresholds = find_gizmo_cost_thresholds(&comb, budget.range.max);
+        crate::calc_probability_from_thresholds(&mut cost_thresholds, budget, comb.probability);
+        probability += cost_thresholds
+            .iter()
+            .filter(|x| {
+                if any_second {
+                    x.contains(wanted)
+                } else {
+                    x.same(wanted)
+                }
+            })
+            .map(|x| x.probability)
+            .sum::<f64>();
+    }
+    probability
+}
+
+/// Move the probability of the ranks of a perk to rank 0
+fn without_ranks(perk_values: &PerkValuesVec, perk: usize, ranks: &[usize]) -> PerkValuesVec {
+    let mut perk_values = perk_values.clone();
+    let values = &mut perk_values[perk];
+    let probabilities = &mut *values.ranks;
+    for rank in ranks {
+        probabilities[0].probability += probabilities[*rank].probability;
+        probabilities[*rank].probability = 0.0;
+    }
+    values.i_first = 0;
+    values.i_last = (0..=values.i_last)
+        .rev()
+        .find(|x| values.ranks[*x].probability > 0.0)
+        .unwrap_or(0);
+    perk_values
+}
+
+fn gives_perk(
+    data: &Data,
+    mat: MaterialName,
+    gizmo_type: GizmoType,
+    is_ancient: bool,
+    perk: PerkName,
+) -> bool {
+    (is_ancient || !data.comps[mat].ancient_only)
+        && data.comps[mat][gizmo_type].iter().any(|x| x.perk == perk)
+}
+
+impl fmt::Display for BlockerReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Probability of {}: {}%",
+            gizmo_text(&self.wanted, self.any_second).cyan(),
+            format_float(self.probability)
+        )?;
+        let blockers = self
+            .blockers
+            .iter()
+            .filter(|x| x.lost.abs() > 1e-12)
+            .collect_vec();
+        if blockers.is_empty() {
+            return writeln!(f, "No other perk changes this probability.");
+        }
+
+        writeln!(f, "\n{}", "Blockers".bright_green().underline())?;
+        for blocker in blockers {
+            writeln!(
+                f,
+                " {} from {} {} {}%",
+                blocker.perk.to_string().cyan(),
+                blocker.materials.iter().join(", "),
+                if blocker.lost >= 0.0 { "costs" } else { "adds" },
+                format_float(blocker.lost.abs())
+            )?;
+            if blocker.ranks.len() > 1 {
+                for (rank, lost) in blocker.ranks.iter() {
+                    writeln!(
+                        f,
+                        "   rank {rank} {} {}%",
+                        if *lost >= 0.0 { "costs" } else { "adds" },
+                        format_float(lost.abs())
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn gizmo_text(gizmo: &Gizmo, any_second: bool) -> String {
+    let first = format!("{} {}", gizmo.perks.0.name, gizmo.perks.0.rank);
+    if any_second {
+        first
+    } else if gizmo.perks.1.is_empty() {
+        format!("{first} + Empty")
+    } else {
+        format!("{first} + {} {}", gizmo.perks.1.name, gizmo.perks.1.rank)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc_gizmo_probabilities;
+
+    #[test]
+    fn blockers_of_precise() {
+        use MaterialName::*;
+        let data = Data::load();
+        let budget = Budget::create(120, false);
+        let materials = vec![
+            PreciseComponents,
+            ArmadylComponents,
+            ArmadylComponents,
+            ConnectorParts,
+        ];
+        let wanted = Gizmo {
+            perks: (
+                Perk {
+                    name: PerkName::Precise,
+                    rank: 3,
+                },
+                Perk::default(),
+            ),
+            ..Default::default()
+        };
+        let report = find_blockers(
+            &data,
+            &budget,
+            &materials,
+            GizmoType::Weapon,
+            false,
+            wanted,
+            false,
+        );
+
+        let expected: f64 =
+            calc_gizmo_probabilities(&data, &budget, &materials, GizmoType::Weapon, false)
+                .iter()
+                .filter(|x| x.same(&wanted))
+                .map(|x| x.probability)
+                .sum();
+        assert!((report.probability - expected).abs() < 1e-12);
+
+        assert!(!report.blockers.is_empty());
+        assert!(report.blockers.iter().all(|x| x.perk != PerkName::Precise));
+        // Without any second perk the gizmo can only be Precise 3 alone more often
+        assert!(report.blockers.iter().all(|x| x.lost >= 0.0));
+        let blunted = report
+            .blockers
+            .iter()
+            .find(|x| x.perk == PerkName::Blunted)
+            .unwrap();
+        assert!(blunted.lost > 0.0);
+        assert_eq!(blunted.materials, vec![PreciseComponents]);
+        assert!(blunted.ranks.iter().all(|x| x.1 <= blunted.lost + 1e-12));
+    }
+}
@@ -1,38 +1,51 @@
+pub mod disassembly;
+pub mod providers;
+pub mod shells;
+
 use crate::{prelude::*, utils::print_warning};
+use colored::Colorize;
+use derive_more::Display;
+use disassembly::{cheapest_sources, load_disassembly_table, DisassemblySource};
 use itertools::Itertools;
+use providers::{create_price_provider, PriceProvider};
 use regex::Regex;
-use serde_json::{self, Value};
-use std::{fs, str::FromStr, sync::RwLock};
+use shells::{load_shell_recipes, ShellRecipes};
+use std::{
+    fs,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 use strum::EnumCount;
 
-static APP_USER_AGENT: &str = concat!(
-    env!("CARGO_PKG_NAME"),
-    "/",
-    env!("CARGO_PKG_VERSION"),
-    ";",
-    env!("CARGO_PKG_REPOSITORY")
-);
-
-pub static PRICES: RwLock<Option<PriceMap>> = RwLock::new(None);
-static SHELL_PRICE: RwLock<f64> = RwLock::new(0.0);
-
-#[derive(PartialEq)]
+/// Where a price came from. Later layers overwrite the prices of earlier ones, in the order they are declared here.
+#[derive(Debug, Display, Default, Clone, Copy, PartialEq, Eq)]
 pub enum PriceSource {
+    #[default]
+    #[display(fmt = "Missing")]
+    Missing,
+    #[display(fmt = "Online")]
     Online,
+    #[display(fmt = "Local price file")]
     Local,
-    Cache,
+    #[display(fmt = "Disassembly")]
+    Disassembly,
+    #[display(fmt = "Override file")]
+    OverrideFile,
+    #[display(fmt = "Manual")]
+    Manual,
 }
 
+#[derive(Debug, Clone, Default)]
 pub struct PriceMap {
     single: StackMap<MaterialName, f64, { MaterialName::COUNT }>,
-    adjusted: StackMap<MaterialName, f64, { MaterialName::COUNT }>,
+    sources: StackMap<MaterialName, PriceSource, { MaterialName::COUNT }>,
 }
 
 impl PriceMap {
     pub fn new() -> PriceMap {
         PriceMap {
             single: StackMap::new(),
-            adjusted: StackMap::new(),
+            sources: StackMap::new(),
         }
     }
 
@@ -40,18 +53,19 @@ impl PriceMap {
         *self.single.get(mat)
     }
 
+    /// Which layer the price of this material came from
+    pub fn source(&self, mat: MaterialName) -> PriceSource {
+        *self.sources.get(mat)
+    }
+
+    /// Set a price by hand
     pub fn set(&mut self, mat: MaterialName, price: f64) {
-        self.single.insert(mat, price);
-        let adjusted_price = if COMMON_MATERIALS.contains(&mat) {
-            price * 5.0
-        } else {
-            price
-        };
-        self.adjusted.insert(mat, adjusted_price);
+        self.set_with_source(mat, price, PriceSource::Manual);
     }
 
-    fn get_adjusted(&self, mat: MaterialName) -> f64 {
-        *self.adjusted.get(mat)
+    pub fn set_with_source(&mut self, mat: MaterialName, price: f64, source: PriceSource) {
+        self.single.insert(mat, price);
+        self.sources.insert(mat, source);
     }
 
     pub fn iter(&self) -> std::iter::Zip<MaterialNameIter, std::slice::Iter<'_, f64>> {
@@ -59,190 +73,555 @@ impl PriceMap {
     }
 }
 
-pub fn calc_gizmo_price(mat_combination: &[MaterialName], prob_gizmo: f64) -> f64 {
-    let shell_price = *SHELL_PRICE.read().unwrap();
-    let prices = PRICES.read().unwrap();
-    if let Some(prices) = prices.as_ref() {
-        let price = shell_price
-            + mat_combination
-                .iter()
-                .fold(0.0, |acc, x| acc + prices.get_adjusted(*x));
+/// Problem found while loading the prices
+#[derive(Debug, Clone, PartialEq)]
+pub enum PriceIssue {
+    /// None of the layers has a price for this material
+    Missing(MaterialName),
+    /// The price list contains a name that doesn't match any material
+    UnknownMaterial(String),
+    /// The price of a material could not be parsed
+    Unparsable {
+        material: MaterialName,
+        value: String,
+    },
+    /// The local prices are older than the max age and could not be refreshed
+    Stale { age_hours: u64 },
+}
 
-        price / prob_gizmo
-    } else {
-        0.0
+impl std::fmt::Display for PriceIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PriceIssue::Missing(mat) => {
+                write!(f, "No price found for {}", mat.to_string().yellow())
+            }
+            PriceIssue::UnknownMaterial(name) => {
+                write!(
+                    f,
+                    "Price list contains unknown material '{}'",
+                    name.yellow()
+                )
+            }
+            PriceIssue::Unparsable { material, value } => write!(
+                f,
+                "Failed to parse price '{}' for {}",
+                value.yellow(),
+                material.to_string().yellow()
+            ),
+            PriceIssue::Stale { age_hours } => write!(
+                f,
+                "Using local prices that are {} hours old because they could not be refreshed",
+                age_hours.to_string().yellow()
+            ),
+        }
     }
 }
 
-pub fn load_component_prices(
-    local_price_file_path: &Option<String>,
-    prefer_online: bool,
-) -> Result<PriceSource, String> {
-    let mut source = PriceSource::Cache;
-    let mut prices = None;
+pub struct LoadedPrices {
+    pub prices: PriceMap,
+    /// Source of the base layer
+    pub source: PriceSource,
+    pub issues: Vec<PriceIssue>,
+    /// Items the disassembly prices came from
+    pub disassembly: Vec<DisassemblySource>,
+    pub shells: ShellRecipes,
+}
 
-    // Don't need to set prices again if the calc is invoked multiple times
-    if PRICES.read().unwrap().is_some() {
-        return Ok(source);
+impl LoadedPrices {
+    /// Print the issues as warnings, or refuse them all in strict mode
+    pub fn check(&self, strict: bool) -> Result<(), String> {
+        if strict && !self.issues.is_empty() {
+            return Err(format!(
+                "Refusing to solve with incomplete prices:\n{}",
+                self.issues.iter().map(|x| format!(" - {x}")).join("\n")
+            ));
+        }
+        for issue in self.issues.iter() {
+            print_warning(issue.to_string().as_str());
+        }
+        Ok(())
     }
+}
 
-    if prefer_online {
-        prices = lookup_on_wiki().ok();
-        source = PriceSource::Online;
+/// Load the prices in layers where each layer overwrites the prices set by the previous ones:
+/// 1. The base prices, fetched from the price provider or loaded from the local price file
+/// 2. The cheapest effective cost of each material according to the disassembly table, when it is lower than the base
+///    price or when disassembly is preferred
+/// 3. The override file, for prices that should stay fixed no matter what the base source says. This file is only
+///    ever read.
+/// 4. The overrides given for this run
+///
+/// Materials that didn't get a price from any layer are reported as [`PriceIssue::Missing`]. The shell recipes are
+/// loaded along with the prices.
+pub fn load_layered_prices(args: &Args, prefer_online: bool) -> Result<LoadedPrices, String> {
+    let max_age = args.price_max_age.map(|x| Duration::from_secs(x * 3600));
+    let provider = create_price_provider(args.price_provider, args.price_endpoint.clone());
+    let mut loaded =
+        load_component_prices(provider.as_ref(), &args.price_file, prefer_online, max_age)?;
+
+    if let Some(file_path) = &args.disassembly_file {
+        let (sources, issues) = cheapest_sources(&load_disassembly_table(file_path)?);
+        // Buying the material stays the cheaper option unless disassembly is preferred
+        let sources = sources
+            .into_iter()
+            .filter(|x| {
+                args.prefer_disassembly
+                    || loaded.prices.source(x.material) == PriceSource::Missing
+                    || x.cost < loaded.prices.get(x.material)
+            })
+            .collect_vec();
+        for source in sources.iter() {
+            loaded
+                .prices
+                .set_with_source(source.material, source.cost, PriceSource::Disassembly);
+        }
+        drop_unparsable(
+            &mut loaded.issues,
+            &sources.iter().map(|x| x.material).collect_vec(),
+        );
+        loaded.issues.extend(issues);
+        loaded.disassembly = sources;
     }
 
-    if let Some(file_path) = local_price_file_path {
-        if prices.is_none() && std::path::Path::new(file_path).exists() {
-            prices = Some(load_from_local_file(file_path)?);
-            source = PriceSource::Local;
+    if let Some(file_path) = &args.price_override_file {
+        if std::path::Path::new(file_path).exists() {
+            let text = fs::read_to_string(file_path)
+                .map_err(|err| format!("Failed to read {}: {}", file_path, err))?;
+            let parsed = parse_price_lines(&text);
+            drop_unparsable(
+                &mut loaded.issues,
+                &parsed.prices.iter().map(|x| x.0).collect_vec(),
+            );
+            for (mat, price) in parsed.prices {
+                loaded
+                    .prices
+                    .set_with_source(mat, price, PriceSource::OverrideFile);
+            }
+            loaded.issues.extend(parsed.issues);
         }
     }
 
-    if prices.is_none() {
-        prices = Some(lookup_on_wiki()?);
-        source = PriceSource::Online;
+    for (mat, price) in args.price_overrides.iter() {
+        loaded
+            .prices
+            .set_with_source(*mat, *price, PriceSource::Manual);
     }
-
-    if let Some(file_path) = local_price_file_path {
-        if let Some(prices) = &prices {
-            let text = prices
-                .iter()
-                .map(|(name, value)| format!("{}: {},", name, value))
-                .sorted()
-                .join("\n");
-
-            fs::write(&file_path, text).unwrap_or_else(|err| {
-                print_warning(format!("Failed to save {}: {}", file_path, err).as_str());
-            });
+    drop_unparsable(
+        &mut loaded.issues,
+        &args.price_overrides.iter().map(|x| x.0).collect_vec(),
+    );
+
+    loaded.shells = load_shell_recipes(&args.shell_recipe_file)?;
+
+    for mat in COMMON_MATERIALS
+        .iter()
+        .chain(UNCOMMON_MATERIALS)
+        .chain(RARE_MATERIALS)
+    {
+        let is_reported = loaded
+            .issues
+            .iter()
+            .any(|x| matches!(x, PriceIssue::Unparsable { material, .. } if material == mat));
+        if loaded.prices.source(*mat) == PriceSource::Missing && !is_reported {
+            loaded.issues.push(PriceIssue::Missing(*mat));
         }
     }
 
-    *PRICES.write().unwrap() = prices;
-    Ok(source)
+    Ok(loaded)
 }
 
-pub fn get_shell_price(gizmo_type: GizmoType, ancient: bool) -> f64 {
-    let prices = PRICES.read().unwrap();
-    let prices = prices.as_ref().unwrap();
-    match ancient {
-        true => match gizmo_type {
-            GizmoType::Armour => {
-                20.0 * prices.get(MaterialName::DeflectingParts)
-                    + 20.0 * prices.get(MaterialName::HistoricComponents)
-                    + 2.0 * prices.get(MaterialName::ClassicComponents)
-                    + 2.0 * prices.get(MaterialName::ProtectiveComponents)
-            }
-            GizmoType::Weapon => {
-                20.0 * prices.get(MaterialName::BladeParts)
-                    + 20.0 * prices.get(MaterialName::HistoricComponents)
-                    + 2.0 * prices.get(MaterialName::ClassicComponents)
-                    + 2.0 * prices.get(MaterialName::StrongComponents)
-            }
-            GizmoType::Tool => {
-                20.0 * prices.get(MaterialName::HeadParts)
-                    + 20.0 * prices.get(MaterialName::HistoricComponents)
-                    + 2.0 * prices.get(MaterialName::ClassicComponents)
-                    + 2.0 * prices.get(MaterialName::PreciseComponents)
-            }
-        },
-        false => match gizmo_type {
-            GizmoType::Armour => {
-                10.0 * prices.get(MaterialName::DeflectingParts)
-                    + 5.0 * prices.get(MaterialName::CraftedParts)
-                    + 2.0 * prices.get(MaterialName::ProtectiveComponents)
-            }
-            GizmoType::Weapon => {
-                10.0 * prices.get(MaterialName::BladeParts)
-                    + 5.0 * prices.get(MaterialName::CraftedParts)
-                    + 2.0 * prices.get(MaterialName::StrongComponents)
+/// Forget the unparsable prices of materials that a later layer gives a price
+fn drop_unparsable(issues: &mut Vec<PriceIssue>, priced: &[MaterialName]) {
+    issues.retain(
+        |x| !matches!(x, PriceIssue::Unparsable { material, .. } if priced.contains(material)),
+    );
+}
+
+/// Load the base prices. Prices fetched from the provider are saved to the local price file together with the time they
+/// were fetched so they can be used instead of the provider until they are older than `max_age`, or as a fallback when
+/// the provider can't be reached. Price files without a fetch time, e.g. written by older versions, use the time they
+/// were last modified instead, and are never stale when that is unknown.
+pub fn load_component_prices(
+    provider: &dyn PriceProvider,
+    local_price_file_path: &Option<String>,
+    prefer_online: bool,
+    max_age: Option<Duration>,
+) -> Result<LoadedPrices, String> {
+    let local = match local_price_file_path {
+        Some(file_path) if std::path::Path::new(file_path).exists() => {
+            Some(load_from_local_file(file_path)?)
+        }
+        _ => None,
+    };
+    let age = local
+        .as_ref()
+        .and_then(|x| {
+            x.fetched_at
+                .or_else(|| modified_time(local_price_file_path.as_ref()?))
+        })
+        .map(|x| unix_time().saturating_sub(x));
+    let is_stale = match (max_age, age) {
+        (Some(max_age), Some(age)) => age > max_age.as_secs(),
+        _ => false,
+    };
+
+    let online = if prefer_online || local.is_none() || is_stale {
+        Some(provider.fetch())
+    } else {
+        None
+    };
+
+    match (online, local) {
+        (Some(Ok(parsed)), _) => {
+            if let Some(file_path) = local_price_file_path {
+                let text = parsed
+                    .prices
+                    .iter()
+                    .map(|(name, value)| format!("{}: {},", name, value))
+                    .sorted()
+                    .join("\n");
+                let text = format!("{FETCHED_AT_HEADER}{}\n{text}", unix_time());
+
+                fs::write(file_path, text).unwrap_or_else(|err| {
+                    print_warning(format!("Failed to save {}: {}", file_path, err).as_str());
+                });
             }
-            GizmoType::Tool => {
-                10.0 * prices.get(MaterialName::HeadParts)
-                    + 5.0 * prices.get(MaterialName::CraftedParts)
-                    + 2.0 * prices.get(MaterialName::PreciseComponents)
+            Ok(parsed.into_loaded(PriceSource::Online))
+        }
+        (_, Some(parsed)) => {
+            let mut loaded = parsed.into_loaded(PriceSource::Local);
+            if is_stale {
+                loaded.issues.push(PriceIssue::Stale {
+                    age_hours: age.unwrap_or_default() / 3600,
+                });
             }
-        },
+            Ok(loaded)
+        }
+        (Some(Err(err)), None) => Err(err),
+        (None, None) => unreachable!(),
     }
 }
 
-pub fn set_shell_price(gizmo_type: GizmoType, ancient: bool) {
-    *SHELL_PRICE.write().unwrap() = get_shell_price(gizmo_type, ancient);
+/// Parse a `<material>=<price>` pair as used for per-run price overrides
+pub fn parse_price_override(text: &str) -> Result<(MaterialName, f64), String> {
+    let (mat, price) = text.split_once('=').ok_or(format!(
+        "Price override '{}' is not of the form material=price",
+        text.yellow()
+    ))?;
+    let mat = MaterialName::from_str(mat.trim())
+        .map_err(|err| format!("{err} '{}'", mat.trim().yellow()))?;
+    let price = price
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid price '{}' for {}", price.trim().yellow(), mat))?;
+    Ok((mat, price))
 }
 
-fn lookup_on_wiki() -> Result<PriceMap, &'static str> {
-    fn _lookup() -> Result<String, reqwest::Error> {
-        println!("Fetching component prices from Runescape.wiki...");
-        let client = reqwest::blocking::Client::builder()
-            .user_agent(APP_USER_AGENT)
-            .build()?;
+fn load_from_local_file(file_path: &str) -> Result<ParsedPrices, String> {
+    let text = match fs::read_to_string(file_path) {
+        Ok(file) => file,
+        Err(err) => return Err(format!("Failed to read {}: {}", file_path, err)),
+    };
 
-        // use p._all_prices() from: https://runescape.wiki/w/Module:Component_costs
-        let url = format!(
-            "https://runescape.wiki/api.php?{}",
-            form_urlencoded::Serializer::new(String::new())
-                .append_pair("action", "parse")
-                .append_pair("text", "{{#invoke:Component costs|_all_prices}}")
-                .append_pair("contentmodel", "wikitext")
-                .append_pair("prop", "text")
-                .append_pair("disablelimitreport", "")
-                .append_pair("wrapoutputclass", "")
-                .append_pair("format", "json")
-                .append_pair("formatversion", "2")
-                .finish()
-        );
-        let body = client.get(url).send()?.text()?;
+    Ok(parse_price_lines(&text))
+}
 
-        Ok(body)
-    }
+static FETCHED_AT_HEADER: &str = "# Fetched at: ";
+
+/// Prices as read from a price list, before they are put in a [`PriceMap`]
+pub struct ParsedPrices {
+    pub prices: Vec<(MaterialName, f64)>,
+    /// Unix time in seconds
+    pub fetched_at: Option<u64>,
+    pub issues: Vec<PriceIssue>,
+}
 
-    match _lookup() {
-        Ok(response) => Ok(string_to_map(&extract_from_response(&response)?)),
-        Err(_) => Err("Failed to fetch prices"),
+impl ParsedPrices {
+    fn into_loaded(self, source: PriceSource) -> LoadedPrices {
+        let mut prices = PriceMap::new();
+        for (mat, price) in self.prices {
+            prices.set_with_source(mat, price, source);
+        }
+        LoadedPrices {
+            prices,
+            source,
+            issues: self.issues,
+            disassembly: vec![],
+            shells: ShellRecipes::default(),
+        }
     }
 }
 
-fn extract_from_response(response: &str) -> Result<String, &'static str> {
-    let json: Value = serde_json::from_str(response).unwrap_or_default();
-    let text = &json["parse"]["text"];
+fn parse_price_lines(text: &str) -> ParsedPrices {
+    let mut parsed = ParsedPrices {
+        prices: vec![],
+        fetched_at: None,
+        issues: vec![],
+    };
 
-    if let Value::String(text) = text {
-        // strip <p>..</p> or <div ...>...</div> wrappers from MediaWiki parse response
-        let re = Regex::new(r"</?(p|div)[^>]*>").unwrap();
-        Ok(re.replace_all(text, "").into_owned())
-    } else {
-        return Err("Unexpected response from Runescape.wiki");
+    // match "(Component): (Price)," lines
+    let re = Regex::new(r"^([^:#]+):\s*([^,\s]*)").unwrap();
+    let lines = text.split('\n');
+
+    for line in lines {
+        if let Some(timestamp) = line.strip_prefix(FETCHED_AT_HEADER) {
+            parsed.fetched_at = timestamp.trim().parse().ok();
+        } else if let Some(captures) = re.captures(line.trim()) {
+            let name = captures[1].trim();
+            match MaterialName::from_str(name) {
+                Ok(mat) => match captures[2].parse() {
+                    Ok(price) => parsed.prices.push((mat, price)),
+                    Err(_) => parsed.issues.push(PriceIssue::Unparsable {
+                        material: mat,
+                        value: captures[2].to_string(),
+                    }),
+                },
+                Err(_) => parsed
+                    .issues
+                    .push(PriceIssue::UnknownMaterial(name.to_string())),
+            }
+        }
     }
+
+    parsed
 }
 
-fn load_from_local_file(file_path: &str) -> Result<PriceMap, String> {
-    let text = match fs::read_to_string(&file_path) {
-        Ok(file) => file,
-        Err(err) => return Err(format!("Failed to read {}: {}", file_path, err)),
-    };
+/// Unix time at which the file was last modified
+fn modified_time(file_path: &str) -> Option<u64> {
+    fs::metadata(file_path)
+        .and_then(|x| x.modified())
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|x| x.as_secs())
+}
 
-    Ok(string_to_map(&text))
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0)
 }
 
-fn string_to_map(text: &str) -> PriceMap {
-    let mut prices = PriceMap::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // match "(Component): (Price)" lines
-    let re = Regex::new(r"^([^:]+): ?([\d\.]+)").unwrap();
-    let lines = text.split('\n');
+    fn temp_file(name: &str, content: &str) -> String {
+        let path =
+            std::env::temp_dir().join(format!("perk_solver_{}_{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        path.to_string_lossy().into_owned()
+    }
 
-    for line in lines {
-        if let Some(captures) = re.captures(line) {
-            if let Ok(mat) = MaterialName::from_str(&captures[1]) {
-                if captures.len() == 3 {
-                    let price: f64 = captures[2].parse().unwrap_or_else(|_| {
-                        print_warning(format!("Failed to parse price for '{}'", mat).as_str());
-                        0.0
-                    });
-                    prices.set(mat, price);
-                }
-            }
+    #[test]
+    fn parse_override() {
+        assert_eq!(
+            parse_price_override("armadyl=150000").unwrap(),
+            (MaterialName::ArmadylComponents, 150000.0)
+        );
+        assert_eq!(
+            parse_price_override("Blade parts = 12.5").unwrap(),
+            (MaterialName::BladeParts, 12.5)
+        );
+        assert!(parse_price_override("armadyl").is_err());
+        assert!(parse_price_override("armadyl=cheap").is_err());
+        assert!(parse_price_override("notamaterial=5").is_err());
+    }
+
+    #[test]
+    fn later_layers_take_precedence() {
+        let price_file = temp_file(
+            "layers_prices.txt",
+            "Armadyl components: 100,\nBlade parts: 1,\nPrecise components: 10,",
+        );
+        let override_file = temp_file(
+            "layers_overrides.txt",
+            "Armadyl components: 200,\nPrecise components: 20,",
+        );
+
+        let args = Args {
+            price_file: Some(price_file.clone()),
+            price_override_file: Some(override_file.clone()),
+            price_overrides: vec![(MaterialName::PreciseComponents, 30.0)],
+            price_max_age: None,
+            ..Default::default()
+        };
+        let LoadedPrices { prices, source, .. } = load_layered_prices(&args, false).unwrap();
+
+        assert_eq!(source, PriceSource::Local);
+        assert_eq!(prices.get(MaterialName::BladeParts), 1.0);
+        assert_eq!(prices.source(MaterialName::BladeParts), PriceSource::Local);
+        assert_eq!(prices.get(MaterialName::ArmadylComponents), 200.0);
+        assert_eq!(
+            prices.source(MaterialName::ArmadylComponents),
+            PriceSource::OverrideFile
+        );
+        assert_eq!(prices.get(MaterialName::PreciseComponents), 30.0);
+        assert_eq!(
+            prices.source(MaterialName::PreciseComponents),
+            PriceSource::Manual
+        );
+
+        // Neither file is rewritten when the prices come from the local price file
+        assert_eq!(
+            fs::read_to_string(&price_file).unwrap(),
+            "Armadyl components: 100,\nBlade parts: 1,\nPrecise components: 10,"
+        );
+        assert_eq!(
+            fs::read_to_string(&override_file).unwrap(),
+            "Armadyl components: 200,\nPrecise components: 20,"
+        );
+        fs::remove_file(price_file).ok();
+        fs::remove_file(override_file).ok();
+    }
+
+    #[test]
+    fn reports_missing_unknown_and_unparsable_prices() {
+        let price_file = temp_file(
+            "issues_prices.txt",
+            "# Fetched at: 0\nArmadyl components: 100,\nNot a material: 5,\nBlade parts: 1.2.3,\n",
+        );
+        let args = Args {
+            price_file: Some(price_file.clone()),
+            price_override_file: None,
+            price_max_age: None,
+            ..Default::default()
+        };
+        let loaded = load_layered_prices(&args, false).unwrap();
+        fs::remove_file(price_file).ok();
+
+        assert_eq!(loaded.prices.get(MaterialName::ArmadylComponents), 100.0);
+        assert!(loaded
+            .issues
+            .contains(&PriceIssue::UnknownMaterial(String::from("Not a material"))));
+        assert!(loaded.issues.contains(&PriceIssue::Unparsable {
+            material: MaterialName::BladeParts,
+            value: String::from("1.2.3")
+        }));
+        assert!(!loaded
+            .issues
+            .contains(&PriceIssue::Missing(MaterialName::BladeParts)));
+        assert!(!loaded
+            .issues
+            .contains(&PriceIssue::Missing(MaterialName::ArmadylComponents)));
+        assert!(loaded
+            .issues
+            .contains(&PriceIssue::Missing(MaterialName::ZarosComponents)));
+        assert!(loaded.check(true).is_err());
+    }
+
+    #[test]
+    fn later_layers_resolve_unparsable_prices() {
+        let price_file = temp_file(
+            "resolved_prices.txt",
+            "Armadyl components: lots,\nBlade parts: 1.2.3,\nPrecise components: ?,",
+        );
+        let override_file = temp_file("resolved_overrides.txt", "Blade parts: 2,");
+        let args = Args {
+            price_file: Some(price_file.clone()),
+            price_override_file: Some(override_file.clone()),
+            price_overrides: vec![(MaterialName::PreciseComponents, 30.0)],
+            price_max_age: None,
+            ..Default::default()
+        };
+        let loaded = load_layered_prices(&args, false).unwrap();
+        fs::remove_file(price_file).ok();
+        fs::remove_file(override_file).ok();
+
+        let unparsable = loaded
+            .issues
+            .iter()
+            .filter_map(|x| match x {
+                PriceIssue::Unparsable { material, .. } => Some(*material),
+                _ => None,
+            })
+            .collect_vec();
+        assert_eq!(unparsable, vec![MaterialName::ArmadylComponents]);
+    }
+
+    #[test]
+    fn disassembly_only_replaces_higher_prices() {
+        let price_file = temp_file(
+            "disassembly_prices.txt",
+            "Blade parts: 10,\nArmadyl components: 100,",
+        );
+        let disassembly_file = temp_file(
+            "disassembly_table.json",
+            r#"[{ "item": "Sword", "price": 100, "yields": { "Blade parts": 20.0, "Armadyl components": 0.5 } }]"#,
+        );
+        let args = Args {
+            price_file: Some(price_file.clone()),
+            price_override_file: None,
+            price_max_age: None,
+            disassembly_file: Some(disassembly_file.clone()),
+            ..Default::default()
+        };
+        let loaded = load_layered_prices(&args, false).unwrap();
+        let preferred = load_layered_prices(
+            &Args {
+                prefer_disassembly: true,
+                ..args
+            },
+            false,
+        )
+        .unwrap();
+        fs::remove_file(price_file).ok();
+        fs::remove_file(disassembly_file).ok();
+
+        assert_eq!(loaded.prices.get(MaterialName::BladeParts), 5.0);
+        assert_eq!(
+            loaded.prices.source(MaterialName::BladeParts),
+            PriceSource::Disassembly
+        );
+        assert_eq!(loaded.prices.get(MaterialName::ArmadylComponents), 100.0);
+        assert_eq!(loaded.disassembly.len(), 1);
+        assert_eq!(preferred.prices.get(MaterialName::ArmadylComponents), 200.0);
+        assert_eq!(preferred.disassembly.len(), 2);
+    }
+
+    struct OfflineProvider;
+
+    impl PriceProvider for OfflineProvider {
+        fn name(&self) -> &str {
+            "offline"
         }
+
+        fn fetch(&self) -> Result<ParsedPrices, String> {
+            Err(String::from("offline"))
+        }
+    }
+
+    #[test]
+    fn price_files_without_fetch_time_use_the_modified_time() {
+        let price_file = temp_file("no_header_prices.txt", "Armadyl components: 100,");
+        let max_age = Some(Duration::from_secs(24 * 3600));
+        let loaded =
+            load_component_prices(&OfflineProvider, &Some(price_file.clone()), false, max_age)
+                .unwrap();
+
+        assert_eq!(loaded.source, PriceSource::Local);
+        assert!(!loaded
+            .issues
+            .iter()
+            .any(|x| matches!(x, PriceIssue::Stale { .. })));
+        assert_eq!(
+            load_component_prices(&OfflineProvider, &Some(price_file.clone()), true, max_age)
+                .unwrap()
+                .source,
+            PriceSource::Local
+        );
+        fs::remove_file(price_file).ok();
     }
 
-    prices
+    #[test]
+    fn reads_fetch_timestamp() {
+        let parsed = parse_price_lines("# Fetched at: 1700000000\nArmadyl components: 100,");
+        assert_eq!(parsed.fetched_at, Some(1700000000));
+        assert_eq!(
+            parsed.prices,
+            vec![(MaterialName::ArmadylComponents, 100.0)]
+        );
+        assert!(parsed.issues.is_empty());
+    }
 }
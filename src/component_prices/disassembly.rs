@@ -0,0 +1,196 @@
+use super::PriceIssue;
+use crate::prelude::*;
+use colored::Colorize;
+use itertools::Itertools;
+use serde::Deserialize;
+use std::{collections::HashMap, fs, str::FromStr};
+
+/// One row of the disassembly table
+#[derive(Debug, Clone, Deserialize)]
+pub struct DisassemblyItem {
+    pub item: String,
+    /// Price of one item
+    pub price: f64,
+    /// Chance (0-1) that a common material roll turns into junk
+    #[serde(default)]
+    pub junk_chance: f64,
+    /// Expected amount of each material when disassembling one item, ignoring junk
+    pub yields: HashMap<String, f64>,
+}
+
+impl DisassemblyItem {
+    /// Expected amount of a material per item, after junk
+    pub fn effective_yield(&self, mat: MaterialName, amount: f64) -> f64 {
+        if COMMON_MATERIALS.contains(&mat) {
+            amount * (1.0 - self.junk_chance)
+        } else {
+            amount
+        }
+    }
+}
+
+/// The cheapest item to disassemble for a material
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisassemblySource {
+    pub material: MaterialName,
+    pub item: String,
+    pub item_price: f64,
+    /// Expected amount of the material per item, after junk
+    pub amount: f64,
+    /// Price of one material
+    pub cost: f64,
+}
+
+/// Read a disassembly table from a JSON file containing a list of [`DisassemblyItem`]s, e.g.
+/// ```json
+/// [{ "item": "Rune platebody", "price": 38000, "junk_chance": 0.0, "yields": { "Plated parts": 11.6 } }]
+/// ```
+pub fn load_disassembly_table(file_path: &str) -> Result<Vec<DisassemblyItem>, String> {
+    let text = fs::read_to_string(file_path)
+        .map_err(|err| format!("Failed to read {}: {}", file_path, err))?;
+    serde_json::from_str(&text).map_err(|err| format!("Failed to parse {}: {}", file_path, err))
+}
+
+/// Find the item with the lowest effective cost for each material in the table. Materials that can't be obtained from
+/// any item are left out.
+pub fn cheapest_sources(items: &[DisassemblyItem]) -> (Vec<DisassemblySource>, Vec<PriceIssue>) {
+    let mut best: HashMap<MaterialName, DisassemblySource> = HashMap::new();
+    let mut issues = vec![];
+
+    for item in items {
+        for (name, amount) in item.yields.iter() {
+            let mat = match MaterialName::from_str(name) {
+                Ok(mat) => mat,
+                Err(_) => {
+                    let issue = PriceIssue::UnknownMaterial(name.clone());
+                    if !issues.contains(&issue) {
+                        issues.push(issue);
+                    }
+                    continue;
+                }
+            };
+            let amount = item.effective_yield(mat, *amount);
+            if amount <= 0.0 {
+                continue;
+            }
+            let cost = item.price / amount;
+            if !best.get(&mat).is_some_and(|x| x.cost <= cost) {
+                best.insert(
+                    mat,
+                    DisassemblySource {
+                        material: mat,
+                        item: item.item.clone(),
+                        item_price: item.price,
+                        amount,
+                        cost,
+                    },
+                );
+            }
+        }
+    }
+
+    let sources = best
+        .into_values()
+        .sorted_by_key(|x| x.material.to_string())
+        .collect_vec();
+    (sources, issues)
+}
+
+/// Table explaining which item each disassembly price came from
+pub fn disassembly_report(sources: &[DisassemblySource]) -> String {
+    let mut text = format!("{}\n", "Disassembly prices".underline().bright_green());
+    for source in sources {
+        text += &format!(
+            " - {}: {} from {} ({:.2} per item at {} each)\n",
+            source.material.to_string().cyan(),
+            format!("{:.2}", source.cost).cyan(),
+            source.item.yellow(),
+            source.amount,
+            source.item_price,
+        );
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str, price: f64, junk_chance: f64, yields: &[(&str, f64)]) -> DisassemblyItem {
+        DisassemblyItem {
+            item: String::from(name),
+            price,
+            junk_chance,
+            yields: yields.iter().map(|(k, v)| (k.to_string(), *v)).collect(),
+        }
+    }
+
+    #[test]
+    fn picks_cheapest_item_per_material() {
+        let items = [
+            item(
+                "Expensive",
+                1000.0,
+                0.0,
+                &[("Blade parts", 10.0), ("Armadyl components", 1.0)],
+            ),
+            item("Cheap", 100.0, 0.0, &[("Blade parts", 5.0)]),
+        ];
+        let (sources, issues) = cheapest_sources(&items);
+
+        assert!(issues.is_empty());
+        assert_eq!(sources.len(), 2);
+        let blade = sources
+            .iter()
+            .find(|x| x.material == MaterialName::BladeParts)
+            .unwrap();
+        assert_eq!(blade.item, "Cheap");
+        assert_eq!(blade.cost, 20.0);
+        let armadyl = sources
+            .iter()
+            .find(|x| x.material == MaterialName::ArmadylComponents)
+            .unwrap();
+        assert_eq!(armadyl.item, "Expensive");
+        assert_eq!(armadyl.cost, 1000.0);
+    }
+
+    #[test]
+    fn junk_only_reduces_common_materials() {
+        let items = [item(
+            "Junky",
+            100.0,
+            0.5,
+            &[("Blade parts", 10.0), ("Precise components", 1.0)],
+        )];
+        let (sources, _) = cheapest_sources(&items);
+
+        let blade = sources
+            .iter()
+            .find(|x| x.material == MaterialName::BladeParts)
+            .unwrap();
+        assert_eq!(blade.amount, 5.0);
+        assert_eq!(blade.cost, 20.0);
+        let precise = sources
+            .iter()
+            .find(|x| x.material == MaterialName::PreciseComponents)
+            .unwrap();
+        assert_eq!(precise.cost, 100.0);
+    }
+
+    #[test]
+    fn reports_unknown_materials() {
+        let items = [item(
+            "Odd",
+            100.0,
+            0.0,
+            &[("Not a material", 1.0), ("Blade parts", 0.0)],
+        )];
+        let (sources, issues) = cheapest_sources(&items);
+
+        assert!(sources.is_empty());
+        assert_eq!(
+            issues,
+            vec![PriceIssue::UnknownMaterial(String::from("Not a material"))]
+        );
+    }
+}
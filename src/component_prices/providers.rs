@@ -0,0 +1,376 @@
+use super::{drop_unparsable, parse_price_lines, ParsedPrices, PriceIssue};
+use crate::{prelude::*, utils::print_warning};
+use itertools::Itertools;
+use regex::Regex;
+use serde_json::{self, Value};
+use std::str::FromStr;
+
+static APP_USER_AGENT: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION"),
+    ";",
+    env!("CARGO_PKG_REPOSITORY")
+);
+
+/// Online source of component prices
+pub trait PriceProvider {
+    /// Name shown to the user
+    fn name(&self) -> &str;
+
+    fn fetch(&self) -> Result<ParsedPrices, String>;
+}
+
+pub fn create_price_provider(
+    provider_type: PriceProviderType,
+    endpoint: Option<String>,
+) -> Box<dyn PriceProvider> {
+    match provider_type {
+        PriceProviderType::Wiki => Box::new(WikiPriceProvider {
+            endpoint: endpoint.unwrap_or(String::from(WikiPriceProvider::DEFAULT_ENDPOINT)),
+        }),
+        PriceProviderType::Ge => Box::new(GeApiPriceProvider {
+            endpoint: endpoint.unwrap_or(String::from(GeApiPriceProvider::DEFAULT_ENDPOINT)),
+            fallback: Some(Box::new(WikiPriceProvider {
+                endpoint: String::from(WikiPriceProvider::DEFAULT_ENDPOINT),
+            })),
+        }),
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+
+/// Prices of the [Component costs](https://runescape.wiki/w/Module:Component_costs) module, fetched through the
+/// MediaWiki parse API
+pub struct WikiPriceProvider {
+    pub endpoint: String,
+}
+
+impl WikiPriceProvider {
+    pub const DEFAULT_ENDPOINT: &'static str = "https://runescape.wiki/api.php";
+}
+
+impl PriceProvider for WikiPriceProvider {
+    fn name(&self) -> &str {
+        "Runescape.wiki"
+    }
+
+    fn fetch(&self) -> Result<ParsedPrices, String> {
+        // use p._all_prices() from: https://runescape.wiki/w/Module:Component_costs
+        let url = format!(
+            "{}?{}",
+            self.endpoint,
+            form_urlencoded::Serializer::new(String::new())
+                .append_pair("action", "parse")
+                .append_pair("text", "{{#invoke:Component costs|_all_prices}}")
+                .append_pair("contentmodel", "wikitext")
+                .append_pair("prop", "text")
+                .append_pair("disablelimitreport", "")
+                .append_pair("wrapoutputclass", "")
+                .append_pair("format", "json")
+                .append_pair("formatversion", "2")
+                .finish()
+        );
+        let response = get(self.name(), &url)?;
+
+        Ok(parse_price_lines(&extract_from_response(&response)?))
+    }
+}
+
+fn extract_from_response(response: &str) -> Result<String, String> {
+    let json: Value = serde_json::from_str(response).unwrap_or_default();
+    let text = &json["parse"]["text"];
+
+    if let Value::String(text) = text {
+        // strip <p>..</p> or <div ...>...</div> wrappers from MediaWiki parse response
+        let re = Regex::new(r"</?(p|div)[^>]*>").unwrap();
+        Ok(re.replace_all(text, "").into_owned())
+    } else {
+        Err(String::from("Unexpected response from Runescape.wiki"))
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+
+/// Latest Grand Exchange prices from the [Weird Gloop exchange API](https://api.weirdgloop.org/#/exchange). Most
+/// components can't be traded, so the API only knows the price of a few of them. The prices of the others are taken from
+/// the fallback provider, the wiki unless the provider is created by hand.
+pub struct GeApiPriceProvider {
+    pub endpoint: String,
+    pub fallback: Option<Box<dyn PriceProvider>>,
+}
+
+impl GeApiPriceProvider {
+    pub const DEFAULT_ENDPOINT: &'static str =
+        "https://api.weirdgloop.org/exchange/history/rs/latest";
+}
+
+impl PriceProvider for GeApiPriceProvider {
+    fn name(&self) -> &str {
+        "the Grand Exchange API"
+    }
+
+    fn fetch(&self) -> Result<ParsedPrices, String> {
+        let materials = COMMON_MATERIALS
+            .iter()
+            .chain(UNCOMMON_MATERIALS)
+            .chain(RARE_MATERIALS)
+            .copied()
+            .collect_vec();
+        let names = materials.iter().join("|");
+        let url = format!(
+            "{}?{}",
+            self.endpoint,
+            form_urlencoded::Serializer::new(String::new())
+                .append_pair("name", &names)
+                .finish()
+        );
+        let response = get(self.name(), &url)?;
+        let mut parsed = parse_ge_response(&response)?;
+
+        let missing = materials
+            .into_iter()
+            .filter(|mat| !parsed.prices.iter().any(|(x, _)| x == mat))
+            .collect_vec();
+        if let (Some(fallback), false) = (&self.fallback, missing.is_empty()) {
+            match fallback.fetch() {
+                Ok(fallback_prices) => {
+                    let prices = fallback_prices
+                        .prices
+                        .into_iter()
+                        .filter(|(mat, _)| missing.contains(mat))
+                        .collect_vec();
+                    drop_unparsable(
+                        &mut parsed.issues,
+                        &prices.iter().map(|x| x.0).collect_vec(),
+                    );
+                    parsed.prices.extend(prices);
+                }
+                Err(err) => print_warning(&format!(
+                    "{err}, only the materials of {} have a price",
+                    self.name()
+                )),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// The response maps each item name to its latest price, e.g.
+/// `{"Armadyl components": {"id": "...", "timestamp": "...", "price": 123, "volume": null}}`, or is of the form
+/// `{"success": false, "error": "..."}`
+fn parse_ge_response(response: &str) -> Result<ParsedPrices, String> {
+    let json: Value = serde_json::from_str(response)
+        .map_err(|_| String::from("Unexpected response from the Grand Exchange API"))?;
+    let items = json.as_object().ok_or(String::from(
+        "Unexpected response from the Grand Exchange API",
+    ))?;
+    if let Some(Value::String(err)) = items.get("error") {
+        return Err(format!("The Grand Exchange API returned an error: {err}"));
+    }
+
+    let mut parsed = ParsedPrices {
+        prices: vec![],
+        fetched_at: None,
+        issues: vec![],
+    };
+    for (name, item) in items {
+        match MaterialName::from_str(name) {
+            Ok(mat) => match item["price"].as_f64() {
+                Some(price) => parsed.prices.push((mat, price)),
+                None => parsed.issues.push(PriceIssue::Unparsable {
+                    material: mat,
+                    value: item["price"].to_string(),
+                }),
+            },
+            Err(_) => parsed
+                .issues
+                .push(PriceIssue::UnknownMaterial(name.to_string())),
+        }
+    }
+
+    Ok(parsed)
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+
+fn get(name: &str, url: &str) -> Result<String, String> {
+    fn _get(url: &str) -> Result<String, reqwest::Error> {
+        let client = reqwest::blocking::Client::builder()
+            .user_agent(APP_USER_AGENT)
+            .build()?;
+        client.get(url).send()?.error_for_status()?.text()
+    }
+
+    eprintln!("Fetching component prices from {name}...");
+    _get(url).map_err(|_| format!("Failed to fetch prices from {name}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread::{self, JoinHandle},
+    };
+
+    /// Serves a single request with the given status and body. Returns the url of the server and a handle that
+    /// resolves to the request line that was received.
+    fn mock_server(status: &'static str, body: &'static str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/api", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![];
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            let response = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+            String::from_utf8_lossy(&request)
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn wiki_provider_parses_prices() {
+        let (url, server) = mock_server(
+            "200 OK",
+            r#"{"parse": {"title": "API", "pageid": 0, "text": "<div class=\"mw-parser-output\"><p>Armadyl components: 1234.5,\nBlade parts: 2,\n</p></div>"}}"#,
+        );
+        let provider = WikiPriceProvider { endpoint: url };
+        let parsed = provider.fetch().unwrap();
+        let request = server.join().unwrap();
+
+        assert!(request.starts_with("GET /api?action=parse"));
+        assert_eq!(
+            parsed.prices,
+            vec![
+                (MaterialName::ArmadylComponents, 1234.5),
+                (MaterialName::BladeParts, 2.0)
+            ]
+        );
+        assert!(parsed.issues.is_empty());
+    }
+
+    #[test]
+    fn wiki_provider_rejects_unexpected_response() {
+        let (url, server) = mock_server("200 OK", r#"{"error": {"code": "badvalue"}}"#);
+        let provider = WikiPriceProvider { endpoint: url };
+        assert!(provider.fetch().is_err());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn ge_provider_parses_prices() {
+        let (url, server) = mock_server(
+            "200 OK",
+            r#"{
+                "Armadyl components": {"id": "1", "timestamp": "2023-07-01T00:00:00.000Z", "price": 150000, "volume": null},
+                "Precise components": {"id": "2", "timestamp": "2023-07-01T00:00:00.000Z", "price": null, "volume": null},
+                "Rune platebody": {"id": "3", "timestamp": "2023-07-01T00:00:00.000Z", "price": 5, "volume": 10}
+            }"#,
+        );
+        let provider = GeApiPriceProvider {
+            endpoint: url,
+            fallback: None,
+        };
+        let parsed = provider.fetch().unwrap();
+        let request = server.join().unwrap();
+
+        assert!(request.starts_with("GET /api?name=Base+parts%7CBlade+parts%7C"));
+        assert_eq!(
+            parsed.prices,
+            vec![(MaterialName::ArmadylComponents, 150000.0)]
+        );
+        assert!(parsed.issues.contains(&PriceIssue::Unparsable {
+            material: MaterialName::PreciseComponents,
+            value: String::from("null")
+        }));
+        assert!(parsed
+            .issues
+            .contains(&PriceIssue::UnknownMaterial(String::from("Rune platebody"))));
+    }
+
+    #[test]
+    fn ge_provider_falls_back_for_missing_materials() {
+        let (ge_url, ge_server) = mock_server(
+            "200 OK",
+            r#"{
+                "Armadyl components": {"id": "1", "timestamp": "2023-07-01T00:00:00.000Z", "price": 150000, "volume": null},
+                "Precise components": {"id": "2", "timestamp": "2023-07-01T00:00:00.000Z", "price": null, "volume": null}
+            }"#,
+        );
+        let (wiki_url, wiki_server) = mock_server(
+            "200 OK",
+            r#"{"parse": {"text": "<p>Armadyl components: 1,\nPrecise components: 2,\nBlade parts: 3,\n</p>"}}"#,
+        );
+        let provider = GeApiPriceProvider {
+            endpoint: ge_url,
+            fallback: Some(Box::new(WikiPriceProvider { endpoint: wiki_url })),
+        };
+        let parsed = provider.fetch().unwrap();
+        ge_server.join().unwrap();
+        wiki_server.join().unwrap();
+
+        assert_eq!(
+            parsed.prices,
+            vec![
+                (MaterialName::ArmadylComponents, 150000.0),
+                (MaterialName::PreciseComponents, 2.0),
+                (MaterialName::BladeParts, 3.0)
+            ]
+        );
+        assert!(parsed.issues.is_empty());
+    }
+
+    #[test]
+    fn ge_provider_returns_api_errors() {
+        let (url, server) = mock_server(
+            "200 OK",
+            r#"{"success": false, "error": "Item(s) not found in the database"}"#,
+        );
+        let provider = GeApiPriceProvider {
+            endpoint: url,
+            fallback: None,
+        };
+        let err = provider.fetch().err().unwrap();
+        server.join().unwrap();
+
+        assert!(err.contains("Item(s) not found in the database"));
+    }
+
+    #[test]
+    fn http_errors_fail_the_fetch() {
+        let (url, server) = mock_server("500 Internal Server Error", "{}");
+        let provider = GeApiPriceProvider {
+            endpoint: url,
+            fallback: None,
+        };
+        assert!(provider.fetch().is_err());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn unreachable_endpoint_fails_the_fetch() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/api", listener.local_addr().unwrap());
+        drop(listener);
+        let provider = WikiPriceProvider { endpoint: url };
+        assert!(provider.fetch().is_err());
+    }
+}
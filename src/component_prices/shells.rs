@@ -0,0 +1,258 @@
+use super::PriceMap;
+use crate::prelude::*;
+use clap::ValueEnum;
+use colored::Colorize;
+use std::{fs, str::FromStr};
+
+/// What a gizmo shell costs
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShellCost {
+    /// Made from materials, with the amount of each
+    Recipe(Vec<(u32, MaterialName)>),
+    /// Bought at a fixed price, or 0 when the shells are already owned
+    Fixed(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShellRecipe {
+    pub gizmo_type: GizmoType,
+    pub ancient: bool,
+    pub cost: ShellCost,
+}
+
+impl ShellRecipe {
+    pub fn name(&self) -> String {
+        if self.ancient {
+            format!(
+                "Ancient {} gizmo shell",
+                self.gizmo_type.to_string().to_lowercase()
+            )
+        } else {
+            format!("{} gizmo shell", self.gizmo_type)
+        }
+    }
+
+    pub fn price(&self, prices: &PriceMap) -> f64 {
+        match &self.cost {
+            ShellCost::Recipe(recipe) => recipe
+                .iter()
+                .map(|(amount, mat)| *amount as f64 * prices.get(*mat))
+                .sum(),
+            ShellCost::Fixed(price) => *price,
+        }
+    }
+}
+
+/// The recipe of each of the six gizmo shells
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShellRecipes {
+    recipes: Vec<ShellRecipe>,
+}
+
+impl ShellRecipes {
+    pub fn get(&self, gizmo_type: GizmoType, ancient: bool) -> &ShellRecipe {
+        self.recipes
+            .iter()
+            .find(|x| x.gizmo_type == gizmo_type && x.ancient == ancient)
+            .unwrap()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, ShellRecipe> {
+        self.recipes.iter()
+    }
+
+    /// Replace the recipes of the shells listed in the text. Each line is of the form `<shell>: <cost>` where the shell
+    /// is the gizmo type optionally prefixed with `ancient`, and the cost either a comma separated list of
+    /// `<amount> <material>` or a fixed price. Lines starting with `#` are ignored.
+    /// ```text
+    /// Weapon: 10 Blade parts, 5 Crafted parts, 2 Strong components
+    /// Ancient tool: 0
+    /// ```
+    pub fn apply_overrides(&mut self, text: &str) -> Result<(), String> {
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (shell, cost) = line.split_once(':').ok_or(format!(
+                "Shell recipe '{}' is not of the form shell: cost",
+                line.yellow()
+            ))?;
+            let shell = shell.trim().to_lowercase();
+            let (ancient, gizmo_type) = match shell.strip_prefix("ancient") {
+                Some(gizmo_type) => (true, gizmo_type.trim()),
+                None => (false, shell.as_str()),
+            };
+            let gizmo_type = GizmoType::from_str(gizmo_type, true)
+                .map_err(|_| format!("Unknown gizmo shell '{}'", shell.yellow()))?;
+            let cost = parse_shell_cost(cost.trim())?;
+
+            self.recipes
+                .iter_mut()
+                .find(|x| x.gizmo_type == gizmo_type && x.ancient == ancient)
+                .unwrap()
+                .cost = cost;
+        }
+        Ok(())
+    }
+}
+
+impl Default for ShellRecipes {
+    fn default() -> Self {
+        use MaterialName::*;
+
+        let recipe = |gizmo_type, ancient, recipe: &[(u32, MaterialName)]| ShellRecipe {
+            gizmo_type,
+            ancient,
+            cost: ShellCost::Recipe(recipe.to_vec()),
+        };
+        ShellRecipes {
+            recipes: vec![
+                recipe(
+                    GizmoType::Weapon,
+                    false,
+                    &[(10, BladeParts), (5, CraftedParts), (2, StrongComponents)],
+                ),
+                recipe(
+                    GizmoType::Armour,
+                    false,
+                    &[
+                        (10, DeflectingParts),
+                        (5, CraftedParts),
+                        (2, ProtectiveComponents),
+                    ],
+                ),
+                recipe(
+                    GizmoType::Tool,
+                    false,
+                    &[(10, HeadParts), (5, CraftedParts), (2, PreciseComponents)],
+                ),
+                recipe(
+                    GizmoType::Weapon,
+                    true,
+                    &[
+                        (20, BladeParts),
+                        (20, HistoricComponents),
+                        (2, ClassicComponents),
+                        (2, StrongComponents),
+                    ],
+                ),
+                recipe(
+                    GizmoType::Armour,
+                    true,
+                    &[
+                        (20, DeflectingParts),
+                        (20, HistoricComponents),
+                        (2, ClassicComponents),
+                        (2, ProtectiveComponents),
+                    ],
+                ),
+                recipe(
+                    GizmoType::Tool,
+                    true,
+                    &[
+                        (20, HeadParts),
+                        (20, HistoricComponents),
+                        (2, ClassicComponents),
+                        (2, PreciseComponents),
+                    ],
+                ),
+            ],
+        }
+    }
+}
+
+/// Load the default shell recipes with the ones from the file applied on top, if it exists
+pub fn load_shell_recipes(file_path: &Option<String>) -> Result<ShellRecipes, String> {
+    let mut recipes = ShellRecipes::default();
+    if let Some(file_path) = file_path {
+        if std::path::Path::new(file_path).exists() {
+            let text = fs::read_to_string(file_path)
+                .map_err(|err| format!("Failed to read {}: {}", file_path, err))?;
+            recipes
+                .apply_overrides(&text)
+                .map_err(|err| format!("{err} in {file_path}"))?;
+        }
+    }
+    Ok(recipes)
+}
+
+fn parse_shell_cost(text: &str) -> Result<ShellCost, String> {
+    if let Ok(price) = text.parse() {
+        return Ok(ShellCost::Fixed(price));
+    }
+
+    let recipe = text
+        .split(',')
+        .map(|x| {
+            let (amount, mat) = x.trim().split_once(' ').ok_or(format!(
+                "Shell ingredient '{}' is not of the form amount material",
+                x.trim().yellow()
+            ))?;
+            let amount = amount
+                .parse()
+                .map_err(|_| format!("Invalid amount '{}'", amount.yellow()))?;
+            let mat = MaterialName::from_str(mat.trim())
+                .map_err(|err| format!("{err} '{}'", mat.trim().yellow()))?;
+            Ok((amount, mat))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(ShellCost::Recipe(recipe))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_recipes_use_material_prices() {
+        let mut prices = PriceMap::new();
+        prices.set(MaterialName::BladeParts, 2.0);
+        prices.set(MaterialName::CraftedParts, 3.0);
+        prices.set(MaterialName::StrongComponents, 100.0);
+        prices.set(MaterialName::HistoricComponents, 1000.0);
+        prices.set(MaterialName::ClassicComponents, 50.0);
+        let recipes = ShellRecipes::default();
+
+        assert_eq!(
+            recipes.get(GizmoType::Weapon, false).price(&prices),
+            10.0 * 2.0 + 5.0 * 3.0 + 2.0 * 100.0
+        );
+        assert_eq!(
+            recipes.get(GizmoType::Weapon, true).price(&prices),
+            20.0 * 2.0 + 20.0 * 1000.0 + 2.0 * 50.0 + 2.0 * 100.0
+        );
+    }
+
+    #[test]
+    fn overrides_replace_listed_shells() {
+        let mut recipes = ShellRecipes::default();
+        recipes
+            .apply_overrides("# owned already\nAncient tool: 0\nweapon: 5 blade, 1 strong\n")
+            .unwrap();
+
+        assert_eq!(
+            recipes.get(GizmoType::Tool, true).cost,
+            ShellCost::Fixed(0.0)
+        );
+        assert_eq!(
+            recipes.get(GizmoType::Weapon, false).cost,
+            ShellCost::Recipe(vec![
+                (5, MaterialName::BladeParts),
+                (1, MaterialName::StrongComponents)
+            ])
+        );
+        assert_eq!(
+            recipes.get(GizmoType::Armour, false),
+            ShellRecipes::default().get(GizmoType::Armour, false)
+        );
+    }
+
+    #[test]
+    fn rejects_bad_recipes() {
+        let mut recipes = ShellRecipes::default();
+        assert!(recipes.apply_overrides("Sword: 100").is_err());
+        assert!(recipes.apply_overrides("Weapon 100").is_err());
+        assert!(recipes.apply_overrides("Weapon: many blade").is_err());
+        assert!(recipes.apply_overrides("Weapon: 5 notamaterial").is_err());
+    }
+}
@@ -0,0 +1,403 @@
+//! Why materials are conflict materials, how much they grow the search and which of them are the cheapest to exclude
+use crate::{
+    calc_combination_count,
+    prelude::*,
+    result::{format_float, format_price, get_best_wanted_index, ResultPool},
+};
+use colored::Colorize;
+use itertools::Itertools;
+use std::fmt;
+
+/// A perk rank of a material with the same cost as one of the wanted perk ranks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Collision {
+    pub perk: PerkName,
+    pub rank: u8,
+    pub cost: u16,
+    pub wanted: Perk,
+}
+
+pub struct ConflictMaterial {
+    pub material: MaterialName,
+    /// Empty when the wanted perks have the same cost, then every material is a conflict material
+    pub collisions: Vec<Collision>,
+}
+
+/// Excluding this material together with the ones of the previous suggestions
+pub struct Suggestion {
+    pub excluded: MaterialName,
+    pub combination_count: u64,
+    /// Best combination without the excluded materials, `None` when the wanted gizmo can't be made without them
+    pub line: Option<ResultLine>,
+}
+
+pub struct ConflictReport {
+    pub materials: Vec<ConflictMaterial>,
+    pub no_conflict_count: usize,
+    pub ancient: bool,
+    pub combination_count: u64,
+    /// Best combination of the solve
+    pub best: Option<ResultLine>,
+    /// Cumulative, the first ones lose the least. Only known after solving.
+    pub suggestions: Vec<Suggestion>,
+}
+
+/// Perk ranks the material can generate, other than the wanted perks, with the same cost as a wanted perk rank
+pub fn find_collisions(
+    data: &Data,
+    gizmo_type: GizmoType,
+    wanted_gizmo: Gizmo,
+    mat: MaterialName,
+) -> Vec<Collision> {
+    let wanted_cost = |perk: Perk| data.perks[perk.name].ranks[perk.rank as usize].cost;
+    let mut wanted = vec![wanted_gizmo.perks.0];
+    if wanted_gizmo.perks.1.name != PerkName::Empty {
+        wanted.push(wanted_gizmo.perks.1);
+    }
+
+    let mut collisions = vec![];
+    for comp_values in data.comps[mat][gizmo_type].iter() {
+        if wanted.iter().any(|x| x.name == comp_values.perk) {
+            continue;
+        }
+        for perk_rank in data.perks[comp_values.perk].ranks.iter() {
+            if perk_rank.rank == 0 {
+                continue;
+            }
+            if let Some(perk) = wanted.iter().find(|x| wanted_cost(**x) == perk_rank.cost) {
+                collisions.push(Collision {
+                    perk: comp_values.perk,
+                    rank: perk_rank.rank,
+                    cost: perk_rank.cost,
+                    wanted: *perk,
+                });
+            }
+        }
+    }
+    collisions
+}
+
+impl ConflictReport {
+    pub fn new(
+        data: &Data,
+        args: &Args,
+        wanted_gizmo: Gizmo,
+        materials: &SplitMaterials,
+    ) -> ConflictReport {
+        ConflictReport {
+            materials: materials
+                .conflict
+                .iter()
+                .map(|mat| ConflictMaterial {
+                    material: *mat,
+                    collisions: find_collisions(data, args.gizmo_type, wanted_gizmo, *mat),
+                })
+                .collect(),
+            no_conflict_count: materials.no_conflict.len(),
+            ancient: args.ancient,
+            combination_count: calc_combination_count(
+                materials.conflict.len(),
+                materials.no_conflict.len(),
+                args.ancient,
+            ),
+            best: None,
+            suggestions: vec![],
+        }
+    }
+
+    /// Factor by which one more conflict material multiplies the amount of combinations
+    pub fn conflict_multiplier(&self) -> f64 {
+        let count = self.materials.len();
+        if count == 0 {
+            return 1.0;
+        }
+        self.combination_count as f64
+            / calc_combination_count(count - 1, self.no_conflict_count, self.ancient) as f64
+    }
+
+    /// Factor by which one more no-conflict material multiplies the amount of combinations
+    pub fn no_conflict_multiplier(&self) -> f64 {
+        if self.no_conflict_count == 0 {
+            return 1.0;
+        }
+        self.combination_count as f64
+            / calc_combination_count(
+                self.materials.len(),
+                self.no_conflict_count - 1,
+                self.ancient,
+            ) as f64
+    }
+
+    /// Suggest conflict materials to exclude, the ones whose exclusion loses the least for the sort type first. Every
+    /// suggestion excludes the materials of the ones before it too. Uses the pool of the solve, so nothing is solved
+    /// again.
+    pub fn suggest(
+        &mut self,
+        best_per_level: &[Vec<ResultLine>],
+        pool: &ResultPool,
+        sort_type: SortType,
+    ) {
+        let best = match get_best_wanted_index(best_per_level, sort_type) {
+            Some(i) => best_per_level[i][0].clone(),
+            None => return,
+        };
+        let best_without = |excluded: &[MaterialName]| {
+            let without = pool.best_without(excluded);
+            get_best_wanted_index(&without, sort_type).map(|i| without[i][0].clone())
+        };
+
+        let order = self
+            .materials
+            .iter()
+            .map(|x| (x.material, best_without(&[x.material])))
+            .sorted_by(|x, y| {
+                f64::total_cmp(&loss(&best, &x.1, sort_type), &loss(&best, &y.1, sort_type))
+            })
+            .map(|x| x.0)
+            .collect_vec();
+
+        self.suggestions.clear();
+        for i in 1..=order.len() {
+            let line = best_without(&order[..i]);
+            let stop = line.is_none();
+            self.suggestions.push(Suggestion {
+                excluded: order[i - 1],
+                combination_count: calc_combination_count(
+                    self.materials.len() - i,
+                    self.no_conflict_count,
+                    self.ancient,
+                ),
+                line,
+            });
+            if stop {
+                break;
+            }
+        }
+        self.best = Some(best);
+    }
+}
+
+/// How much worse the line is than the best for the sort type, infinite when there is no line
+fn loss(best: &ResultLine, line: &Option<ResultLine>, sort_type: SortType) -> f64 {
+    match line {
+        None => f64::INFINITY,
+        Some(line) => best.sort_key(sort_type) - line.sort_key(sort_type),
+    }
+}
+
+impl fmt::Display for ConflictReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", "Conflict materials".bright_green().underline())?;
+        if self.materials.is_empty() {
+            return writeln!(
+                f,
+                " None, the order of the materials never matters for the wanted gizmo."
+            );
+        }
+        write!(
+            f,
+            " {} combinations. Each conflict material multiplies this by {:.1}",
+            self.combination_count,
+            self.conflict_multiplier()
+        )?;
+        if self.no_conflict_count > 0 {
+            write!(
+                f,
+                ", each no-conflict material by {:.1}",
+                self.no_conflict_multiplier()
+            )?;
+        }
+        writeln!(f, ".")?;
+        for material in self.materials.iter() {
+            writeln!(f, " {}", material.material.to_string().cyan())?;
+            if material.collisions.is_empty() {
+                writeln!(
+                    f,
+                    "   The wanted perks have the same cost, the order of every material matters"
+                )?;
+            }
+            for collision in material.collisions.iter() {
+                writeln!(
+                    f,
+                    "   {} {} has the same cost ({}) as {} {}",
+                    collision.perk,
+                    collision.rank,
+                    collision.cost,
+                    collision.wanted.name,
+                    collision.wanted.rank
+                )?;
+            }
+        }
+
+        let best = match &self.best {
+            Some(best) => best,
+            None => return Ok(()),
+        };
+        writeln!(f, "\n{}", "Suggested exclusions".bright_green().underline())?;
+        writeln!(
+            f,
+            " Best now: {} combinations, gizmo {}%, price {}",
+            self.combination_count,
+            format_float(best.prob_gizmo),
+            format_price(best.price)
+        )?;
+        let width = self
+            .suggestions
+            .iter()
+            .map(|x| x.excluded.to_string().chars().count() + 2)
+            .max()
+            .unwrap_or(0)
+            .max("Also exclude".len());
+        writeln!(
+            f,
+            " {:<width$}  {:>12}  {:>11}  {:>10}",
+            "Also exclude", "Combinations", "Gizmo (%)", "Price"
+        )?;
+        for suggestion in self.suggestions.iter() {
+            let name = format!("+ {}", suggestion.excluded);
+            match &suggestion.line {
+                Some(line) => writeln!(
+                    f,
+                    " {:<width$}  {:>12}  {:>11}  {:>10}",
+                    name,
+                    suggestion.combination_count,
+                    format_float(line.prob_gizmo),
+                    format_price(line.price)
+                )?,
+                None => writeln!(
+                    f,
+                    " {:<width$}  {}",
+                    name,
+                    "no combination can make the gizmo".red()
+                )?,
+            }
+        }
+
+        // Exclusions that still find the same best combination
+        let lossless = self
+            .suggestions
+            .iter()
+            .take_while(|x| {
+                x.line.as_ref().is_some_and(|line| {
+                    line.mat_combination.iter().counts() == best.mat_combination.iter().counts()
+                })
+            })
+            .collect_vec();
+        if let Some(last) = lossless.last() {
+            writeln!(
+                f,
+                "\n Excluding \"{}\" searches {:.1} times fewer combinations and finds the same best combination.",
+                lossless
+                    .iter()
+                    .map(|x| x.excluded.to_string().to_lowercase())
+                    .join(","),
+                self.combination_count as f64 / last.combination_count.max(1) as f64
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn collisions_with_the_wanted_cost() {
+        use MaterialName::*;
+        let data = Data::load();
+        let mobile = Gizmo {
+            perks: (
+                Perk {
+                    name: PerkName::Mobile,
+                    rank: 1,
+                },
+                Perk::default(),
+            ),
+            ..Default::default()
+        };
+        let collisions = find_collisions(&data, GizmoType::Armour, mobile, ConnectorParts);
+        assert!(collisions.contains(&Collision {
+            perk: PerkName::DragonBait,
+            rank: 1,
+            cost: 35,
+            wanted: mobile.perks.0,
+        }));
+        assert!(collisions.iter().all(|x| x.cost == 35));
+
+        let precise = Gizmo {
+            perks: (
+                Perk {
+                    name: PerkName::Precise,
+                    rank: 3,
+                },
+                Perk::default(),
+            ),
+            ..Default::default()
+        };
+        assert!(find_collisions(&data, GizmoType::Weapon, precise, ArmadylComponents).is_empty());
+    }
+
+    #[test]
+    fn suggest_lossless_exclusions_first() {
+        use MaterialName::*;
+        let args = Arc::new(Args {
+            invention_level: InventionLevel::Single(99),
+            ..Default::default()
+        });
+        let mut pool = ResultPool::new(args.clone());
+        for (mats, price) in [
+            (vec![DextrousComponents, FlexibleParts], 100.0),
+            (vec![ConnectorParts, FlexibleParts], 150.0),
+            (vec![FlexibleParts], 300.0),
+        ] {
+            pool.insert(ResultLine {
+                level: 99,
+                prob_gizmo: 0.5,
+                prob_attempt: 0.5,
+                price,
+                mat_combination: Arc::new(mats),
+                ..Default::default()
+            });
+        }
+        let materials = SplitMaterials {
+            conflict: vec![ConnectorParts, DextrousComponents, FlexibleParts],
+            no_conflict: vec![],
+        };
+        let mut report = ConflictReport::new(
+            &Data::load(),
+            &args,
+            Gizmo {
+                perks: (
+                    Perk {
+                        name: PerkName::Mobile,
+                        rank: 1,
+                    },
+                    Perk::default(),
+                ),
+                ..Default::default()
+            },
+            &materials,
+        );
+        report.suggest(&pool.best_without(&[]), &pool, SortType::Price);
+
+        assert_eq!(report.best.as_ref().unwrap().price, 100.0);
+        let excluded = report.suggestions.iter().map(|x| x.excluded).collect_vec();
+        assert_eq!(
+            excluded,
+            vec![ConnectorParts, DextrousComponents, FlexibleParts]
+        );
+        let prices = report
+            .suggestions
+            .iter()
+            .map(|x| x.line.as_ref().map(|x| x.price))
+            .collect_vec();
+        assert_eq!(prices, vec![Some(100.0), Some(300.0), None]);
+        assert!(report
+            .suggestions
+            .iter()
+            .tuple_windows()
+            .all(|(x, y)| x.combination_count > y.combination_count));
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{
+    component_prices::{shells::ShellRecipe, PriceMap},
+    prelude::*,
+};
+
+/// Estimates how much it costs to get a gizmo out of a given material combination.
+///
+/// Only [`CostModel::shell_price`] and [`CostModel::material_price`] have to be implemented. The other methods combine
+/// these two into the expected price of one wanted gizmo and can be overridden when that isn't enough, e.g. to add a
+/// time cost per attempt or to subtract what failed gizmos are worth.
+pub trait CostModel: Send + Sync {
+    /// Price of one gizmo shell
+    fn shell_price(&self) -> f64;
+
+    /// Price of the materials that go into a single slot. For common materials this is the price of 5 of them.
+    fn material_price(&self, mat: MaterialName) -> f64;
+
+    /// Price of one gizmo that does not come out empty, which is the shell plus all the materials in it.
+    fn attempt_cost(&self, mat_combination: &[MaterialName]) -> f64 {
+        self.shell_price()
+            + mat_combination
+                .iter()
+                .fold(0.0, |acc, x| acc + self.material_price(*x))
+    }
+
+    /// Expected price to get one gizmo with the wanted perks.
+    ///
+    /// Empty gizmos don't consume the shell nor the materials so only the probability per consumed gizmo matters.
+    fn gizmo_price(
+        &self,
+        mat_combination: &[MaterialName],
+        prob_gizmo: f64,
+        _prob_attempt: f64,
+    ) -> f64 {
+        self.attempt_cost(mat_combination) / prob_gizmo
+    }
+}
+
+/// Probability to get the wanted gizmo before the budget runs out.
+///
+/// Empty gizmos give the shell and materials back, so the budget pays for `budget / attempt_cost` consumed gizmos
+/// (rounded down) that each succeed with the probability per consumed gizmo.
+pub fn success_within_budget(budget: f64, attempt_cost: f64, prob_gizmo: f64) -> f64 {
+    if prob_gizmo <= 0.0 || attempt_cost.is_nan() || attempt_cost < 0.0 {
+        return 0.0;
+    }
+    if attempt_cost == 0.0 {
+        return 1.0;
+    }
+    let attempts = (budget / attempt_cost).floor();
+    1.0 - (1.0 - prob_gizmo.min(1.0)).powf(attempts)
+}
+
+/// Prices materials at their component price (5 for common materials) and the shell at the cost of its recipe.
+#[derive(Debug, Clone)]
+pub struct DefaultCostModel {
+    prices: PriceMap,
+    shell_price: f64,
+}
+
+impl DefaultCostModel {
+    pub fn new(prices: PriceMap, shell: &ShellRecipe) -> DefaultCostModel {
+        let shell_price = shell.price(&prices);
+        DefaultCostModel {
+            prices,
+            shell_price,
+        }
+    }
+
+    pub fn prices(&self) -> &PriceMap {
+        &self.prices
+    }
+}
+
+impl CostModel for DefaultCostModel {
+    fn shell_price(&self) -> f64 {
+        self.shell_price
+    }
+
+    fn material_price(&self, mat: MaterialName) -> f64 {
+        if COMMON_MATERIALS.contains(&mat) {
+            self.prices.get(mat) * 5.0
+        } else {
+            self.prices.get(mat)
+        }
+    }
+}
+
+/// Cost model where everything is free. Useful when only the probabilities are of interest.
+pub struct FreeCostModel;
+
+impl CostModel for FreeCostModel {
+    fn shell_price(&self) -> f64 {
+        0.0
+    }
+
+    fn material_price(&self, _mat: MaterialName) -> f64 {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component_prices::shells::ShellRecipes;
+
+    struct FixedCostModel;
+
+    impl CostModel for FixedCostModel {
+        fn shell_price(&self) -> f64 {
+            100.0
+        }
+
+        fn material_price(&self, mat: MaterialName) -> f64 {
+            match mat {
+                MaterialName::ArmadylComponents => 0.0,
+                _ => 10.0,
+            }
+        }
+    }
+
+    #[test]
+    fn attempt_cost_sums_shell_and_materials() {
+        let mats = [
+            MaterialName::ArmadylComponents,
+            MaterialName::PreciseComponents,
+            MaterialName::PreciseComponents,
+        ];
+        assert_eq!(FixedCostModel.attempt_cost(&mats), 120.0);
+    }
+
+    #[test]
+    fn budget_pays_for_whole_attempts() {
+        approx::assert_relative_eq!(success_within_budget(250.0, 100.0, 0.5), 0.75);
+        assert_eq!(success_within_budget(99.0, 100.0, 0.5), 0.0);
+        assert_eq!(success_within_budget(100.0, 100.0, 1.0), 1.0);
+        assert_eq!(success_within_budget(100.0, 0.0, 0.1), 1.0);
+        assert_eq!(success_within_budget(1e9, 100.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn default_model_uses_5_common_materials_per_slot() {
+        let mut prices = PriceMap::new();
+        prices.set(MaterialName::BladeParts, 2.0);
+        prices.set(MaterialName::CraftedParts, 3.0);
+        prices.set(MaterialName::StrongComponents, 100.0);
+        prices.set(MaterialName::PreciseComponents, 50.0);
+        let shells = ShellRecipes::default();
+        let model = DefaultCostModel::new(prices, shells.get(GizmoType::Weapon, false));
+
+        assert_eq!(model.shell_price(), 10.0 * 2.0 + 5.0 * 3.0 + 2.0 * 100.0);
+        assert_eq!(model.material_price(MaterialName::BladeParts), 10.0);
+        assert_eq!(model.material_price(MaterialName::PreciseComponents), 50.0);
+    }
+
+    #[test]
+    fn gizmo_price_divides_by_prob_gizmo() {
+        let mats = [MaterialName::PreciseComponents];
+        assert_eq!(FixedCostModel.gizmo_price(&mats, 0.5, 0.25), 220.0);
+    }
+}
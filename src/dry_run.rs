@@ -0,0 +1,546 @@
+//! Size and runtime of a search, estimated before solving
+use crate::{
+    calc_combination_counts_per_slot, calc_wanted_gizmo_probabilities, check_orders,
+    cost_model::CostModel, generate_budgets, prelude::*, result::score_line, sensitivity::XorShift,
+};
+use colored::Colorize;
+use itertools::Itertools;
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// Total time spent measuring, shared by all amounts of used slots
+const CALIBRATION_TIME: Duration = Duration::from_millis(800);
+
+pub struct DryRun {
+    /// Combinations for each amount of used slots, starting at 1
+    pub combinations_per_slot: Vec<u64>,
+    /// Measured time to check one combination on one thread, for each amount of used slots
+    pub seconds_per_combination: Vec<f64>,
+    pub threads: usize,
+    /// Time target in seconds
+    pub target: Option<f64>,
+    /// Only made when there is a target
+    pub plan: Option<ExclusionPlan>,
+}
+
+/// Materials to exclude to get the search within the time target
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExclusionPlan {
+    pub excluded: Vec<MaterialName>,
+    pub combination_count: u64,
+    pub seconds: f64,
+    /// False when the target can't be reached without excluding materials needed for the wanted perks
+    pub reached: bool,
+}
+
+impl DryRun {
+    pub fn combination_count(&self) -> u64 {
+        self.combinations_per_slot.iter().sum()
+    }
+
+    pub fn estimated_seconds(&self) -> f64 {
+        estimate_seconds(
+            &self
+                .combinations_per_slot
+                .iter()
+                .map(|x| *x as f64)
+                .collect_vec(),
+            &self.seconds_per_combination,
+            self.threads,
+        )
+    }
+}
+
+/// Count the combinations per amount of used slots and time a random sample of them on this machine. With a time
+/// target it also plans which materials to exclude, starting with the ones that gave the worst results in the sample.
+pub fn dry_run(
+    data: &Data,
+    args: &Args,
+    wanted_gizmo: Gizmo,
+    materials: &SplitMaterials,
+    cost_model: &dyn CostModel,
+) -> DryRun {
+    let (seconds_per_combination, least_useful) =
+        calibrate(data, args, wanted_gizmo, materials, cost_model);
+    let threads = if args.limit_cpu {
+        (num_cpus::get() * 8 / 10).max(1)
+    } else {
+        num_cpus::get()
+    };
+    let plan = args.time_target.map(|target| {
+        plan_exclusions(
+            data,
+            args,
+            wanted_gizmo,
+            materials,
+            &least_useful,
+            &seconds_per_combination,
+            threads,
+            target,
+        )
+    });
+
+    DryRun {
+        combinations_per_slot: calc_combination_counts_per_slot(
+            materials.conflict.len(),
+            materials.no_conflict.len(),
+            args.ancient,
+        )
+        .iter()
+        .map(|x| (x + 0.5) as u64)
+        .collect(),
+        seconds_per_combination,
+        threads,
+        target: args.time_target,
+        plan,
+    }
+}
+
+fn estimate_seconds(
+    combinations_per_slot: &[f64],
+    seconds_per_combination: &[f64],
+    threads: usize,
+) -> f64 {
+    combinations_per_slot
+        .iter()
+        .zip(seconds_per_combination)
+        .map(|(count, seconds)| count * seconds)
+        .sum::<f64>()
+        / threads as f64
+}
+
+/// Time per combination for each amount of used slots, and all materials ordered from the worst best result in the
+/// sample to the best one. Materials that were in no result that can make the wanted gizmo come first.
+///
+/// Combinations of only no-conflict materials and combinations with conflict materials are timed apart, the latter on
+/// the same path as the solver where every order of the distinct materials is checked. The time per combination is the
+/// average of both, weighted by how many combinations of each there are.
+fn calibrate(
+    data: &Data,
+    args: &Args,
+    wanted_gizmo: Gizmo,
+    materials: &SplitMaterials,
+    cost_model: &dyn CostModel,
+) -> (Vec<f64>, Vec<MaterialName>) {
+    let budgets = generate_budgets(&args.invention_level, args.ancient);
+    let slot_count = if args.ancient { 9 } else { 5 };
+    let all = materials
+        .conflict
+        .iter()
+        .chain(&materials.no_conflict)
+        .copied()
+        .sorted()
+        .collect_vec();
+    let counts = calc_combination_counts_per_slot(
+        materials.conflict.len(),
+        materials.no_conflict.len(),
+        args.ancient,
+    );
+    let no_conflict_counts =
+        calc_combination_counts_per_slot(0, materials.no_conflict.len(), args.ancient);
+    let mut rng = XorShift::new(0x2545f4914f6cdd1d);
+    let mut best: HashMap<MaterialName, ResultLine> = HashMap::new();
+    let mut keep_best = |lines: Vec<ResultLine>| {
+        for mut line in lines {
+            score_line(args, &mut line);
+            let sort_key = line.sort_key(args.sort_type);
+            for mat in line.mat_combination.iter().unique() {
+                if !best
+                    .get(mat)
+                    .is_some_and(|x| x.sort_key(args.sort_type) >= sort_key)
+                {
+                    best.insert(*mat, line.clone());
+                }
+            }
+        }
+    };
+
+    let kinds = [&materials.no_conflict, &materials.conflict]
+        .iter()
+        .filter(|x| !x.is_empty())
+        .count();
+    let time_per_part = CALIBRATION_TIME / (slot_count * kinds.max(1)) as u32;
+
+    let mut seconds_per_combination = vec![];
+    for n_mats_used in 1..=slot_count {
+        let no_conflict_seconds = if materials.no_conflict.is_empty() {
+            0.0
+        } else {
+            time_per_combination(time_per_part, |_| {
+                let mats = (0..n_mats_used)
+                    .map(|_| pick(&mut rng, &materials.no_conflict))
+                    .collect_vec();
+                keep_best(calc_wanted_gizmo_probabilities(
+                    data,
+                    args,
+                    &budgets,
+                    mats,
+                    wanted_gizmo,
+                    cost_model,
+                    &mut None,
+                ));
+                1
+            })
+        };
+        let conflict_seconds = if materials.conflict.is_empty() {
+            0.0
+        } else {
+            time_per_combination(time_per_part, |deadline| {
+                let mut unordered = vec![pick(&mut rng, &materials.conflict)];
+                unordered.extend((1..n_mats_used).map(|_| pick(&mut rng, &all)));
+                let distinct = unordered.iter().copied().unique().collect_vec();
+                for mat in distinct.iter() {
+                    let i = unordered.iter().position(|x| x == mat).unwrap();
+                    unordered.remove(i);
+                }
+                let mut orders = 0;
+                check_orders(
+                    data,
+                    args,
+                    &budgets,
+                    &distinct,
+                    &unordered,
+                    wanted_gizmo,
+                    cost_model,
+                    |lines| {
+                        keep_best(lines);
+                        orders += 1;
+                        // An ancient gizmo can have 9! orders, so stop in the middle of the sample
+                        Instant::now() < deadline
+                    },
+                );
+                orders
+            })
+        };
+
+        let no_conflict_count = no_conflict_counts[n_mats_used - 1];
+        let conflict_count = counts[n_mats_used - 1] - no_conflict_count;
+        seconds_per_combination.push(
+            (no_conflict_count * no_conflict_seconds + conflict_count * conflict_seconds)
+                / counts[n_mats_used - 1].max(1.0),
+        );
+    }
+
+    let least_useful = all
+        .into_iter()
+        .sorted_by(|x, y| {
+            let key = |mat| {
+                best.get(mat)
+                    .map_or(f64::NEG_INFINITY, |line: &ResultLine| {
+                        line.sort_key(args.sort_type)
+                    })
+            };
+            f64::total_cmp(&key(x), &key(y))
+        })
+        .collect();
+    (seconds_per_combination, least_useful)
+}
+
+/// Run samples until the time is up and return the time per combination. Each sample gets the deadline and returns how
+/// many combinations it checked.
+fn time_per_combination(time: Duration, mut sample: impl FnMut(Instant) -> usize) -> f64 {
+    let start = Instant::now();
+    let deadline = start + time;
+    let mut combinations = 0;
+    while Instant::now() < deadline {
+        combinations += sample(deadline);
+    }
+    start.elapsed().as_secs_f64() / combinations.max(1) as f64
+}
+
+fn pick(rng: &mut XorShift, mats: &[MaterialName]) -> MaterialName {
+    mats[(rng.next_f64() * mats.len() as f64) as usize]
+}
+
+/// Exclude materials one at a time until the estimate is within the target. Each step excludes the least useful
+/// conflict or no-conflict material, whichever shrinks the search the most, but never the last material that can give
+/// one of the wanted perks.
+#[allow(clippy::too_many_arguments)]
+fn plan_exclusions(
+    data: &Data,
+    args: &Args,
+    wanted_gizmo: Gizmo,
+    materials: &SplitMaterials,
+    least_useful: &[MaterialName],
+    seconds_per_combination: &[f64],
+    threads: usize,
+    target: f64,
+) -> ExclusionPlan {
+    let estimate = |conflict: usize, no_conflict: usize| {
+        let counts = calc_combination_counts_per_slot(conflict, no_conflict, args.ancient);
+        let seconds = estimate_seconds(&counts, seconds_per_combination, threads);
+        ((counts.iter().sum::<f64>() + 0.5) as u64, seconds)
+    };
+    let gives = |mat: MaterialName, perk: PerkName| {
+        data.comps[mat][args.gizmo_type]
+            .iter()
+            .any(|x| x.perk == perk)
+    };
+    let wanted_perks = [wanted_gizmo.perks.0.name, wanted_gizmo.perks.1.name]
+        .into_iter()
+        .filter(|x| *x != PerkName::Empty)
+        .collect_vec();
+
+    let mut conflict = materials.conflict.clone();
+    let mut no_conflict = materials.no_conflict.clone();
+    let mut excluded = vec![];
+    loop {
+        let (combination_count, seconds) = estimate(conflict.len(), no_conflict.len());
+        if seconds <= target {
+            return ExclusionPlan {
+                excluded,
+                combination_count,
+                seconds,
+                reached: true,
+            };
+        }
+
+        let remaining = conflict.iter().chain(&no_conflict).copied().collect_vec();
+        let required = |mat: MaterialName| {
+            wanted_perks.iter().any(|perk| {
+                gives(mat, *perk) && !remaining.iter().any(|x| *x != mat && gives(*x, *perk))
+            })
+        };
+        let pick = |group: &[MaterialName]| {
+            least_useful
+                .iter()
+                .copied()
+                .find(|x| group.contains(x) && !required(*x))
+        };
+        let from_conflict =
+            pick(&conflict).map(|x| (x, estimate(conflict.len() - 1, no_conflict.len()).1));
+        let from_no_conflict =
+            pick(&no_conflict).map(|x| (x, estimate(conflict.len(), no_conflict.len() - 1).1));
+
+        match [from_conflict, from_no_conflict]
+            .into_iter()
+            .flatten()
+            .min_by(|x, y| f64::total_cmp(&x.1, &y.1))
+        {
+            Some((mat, _)) => {
+                conflict.retain(|x| *x != mat);
+                no_conflict.retain(|x| *x != mat);
+                excluded.push(mat);
+            }
+            None => {
+                return ExclusionPlan {
+                    excluded,
+                    combination_count,
+                    seconds,
+                    reached: false,
+                }
+            }
+        }
+    }
+}
+
+pub fn format_duration(seconds: f64) -> String {
+    let total = seconds.round() as u64;
+    let (hours, mins, secs) = (total / 3600, (total / 60) % 60, total % 60);
+    if hours > 0 {
+        format!("{hours}h {mins:02}m {secs:02}s")
+    } else if mins > 0 {
+        format!("{mins}m {secs:02}s")
+    } else {
+        format!("{seconds:.1}s")
+    }
+}
+
+impl fmt::Display for DryRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", "Dry run".bright_green().underline())?;
+        writeln!(
+            f,
+            " {:>5}  {:>14}  {:>15}",
+            "Slots", "Combinations", "Per combination"
+        )?;
+        for (i, (count, seconds)) in self
+            .combinations_per_slot
+            .iter()
+            .zip(&self.seconds_per_combination)
+            .enumerate()
+        {
+            writeln!(
+                f,
+                " {:>5}  {:>14}  {:>12.1} µs",
+                i + 1,
+                count,
+                seconds * 1e6
+            )?;
+        }
+        writeln!(f, " {:>5}  {:>14}", "Total", self.combination_count())?;
+        writeln!(
+            f,
+            "\n Estimated time on {} thread{}: {}",
+            self.threads,
+            if self.threads == 1 { "" } else { "s" },
+            format_duration(self.estimated_seconds()).cyan()
+        )?;
+
+        if let (Some(target), Some(plan)) = (self.target, &self.plan) {
+            let target = format_duration(target);
+            let excluded = plan
+                .excluded
+                .iter()
+                .map(|x| x.to_string().to_lowercase())
+                .join(",");
+            if plan.excluded.is_empty() {
+                writeln!(
+                    f,
+                    " Within the target of {target}, nothing needs to be excluded."
+                )?;
+            } else if plan.reached {
+                writeln!(
+                    f,
+                    " Exclude \"{}\" to search {} combinations in about {}, within the target of {target}.",
+                    excluded.yellow(),
+                    plan.combination_count,
+                    format_duration(plan.seconds)
+                )?;
+            } else {
+                writeln!(
+                    f,
+                    " The target of {target} can't be reached without excluding materials needed for the wanted perks. \
+                    Excluding \"{}\" leaves {} combinations in about {}.",
+                    excluded.yellow(),
+                    plan.combination_count,
+                    format_duration(plan.seconds)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc_combination_count;
+
+    #[test]
+    fn counts_per_slot_add_up() {
+        for (conflict, no_conflict, ancient) in [(0, 5, false), (3, 7, false), (4, 20, true)] {
+            let per_slot: f64 = calc_combination_counts_per_slot(conflict, no_conflict, ancient)
+                .iter()
+                .sum();
+            assert_eq!(
+                (per_slot + 0.5) as u64,
+                calc_combination_count(conflict, no_conflict, ancient)
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_the_materials_of_the_wanted_perk() {
+        use MaterialName::*;
+        let data = Data::load();
+        let args = Args {
+            invention_level: InventionLevel::Single(120),
+            gizmo_type: GizmoType::Weapon,
+            perk: PerkName::Precise,
+            rank: 3,
+            ..Default::default()
+        };
+        let wanted = Gizmo {
+            perks: (
+                Perk {
+                    name: PerkName::Precise,
+                    rank: 3,
+                },
+                Perk::default(),
+            ),
+            ..Default::default()
+        };
+        let materials = SplitMaterials {
+            conflict: vec![],
+            no_conflict: vec![
+                ArmadylComponents,
+                BladeParts,
+                ConnectorParts,
+                PreciseComponents,
+            ],
+        };
+        let least_useful = [
+            PreciseComponents,
+            ArmadylComponents,
+            BladeParts,
+            ConnectorParts,
+        ];
+        let plan = |target| {
+            plan_exclusions(
+                &data,
+                &args,
+                wanted,
+                &materials,
+                &least_useful,
+                &[1.0; 5],
+                1,
+                target,
+            )
+        };
+
+        let all = plan(f64::INFINITY);
+        assert!(all.reached);
+        assert!(all.excluded.is_empty());
+
+        let none = plan(0.0);
+        assert!(!none.reached);
+        // Connector parts are the last material left that gives Precise
+        assert_eq!(
+            none.excluded,
+            vec![PreciseComponents, ArmadylComponents, BladeParts]
+        );
+        assert_eq!(none.combination_count, 5);
+
+        let some = plan(100.0);
+        assert!(some.reached);
+        assert!(some.seconds <= 100.0);
+        assert_eq!(some.excluded[0], PreciseComponents);
+    }
+
+    #[test]
+    fn counts_the_combinations_the_solver_checks() {
+        use crate::{
+            component_prices::{shells::ShellRecipes, PriceMap},
+            Solver,
+        };
+        use std::sync::atomic::Ordering::Relaxed;
+        use strum::IntoEnumIterator;
+
+        let mut prices = PriceMap::new();
+        for mat in MaterialName::iter() {
+            prices.set(mat, 1.0);
+        }
+        // Only conflict materials, so every order is checked
+        let args = Args {
+            invention_level: InventionLevel::Single(99),
+            gizmo_type: GizmoType::Armour,
+            perk: PerkName::Mobile,
+            rank: 1,
+            out_file: None,
+            price_file: None,
+            ..Default::default()
+        };
+        let solver = || {
+            Solver::new(
+                args.clone(),
+                Data::load(),
+                prices.clone(),
+                &ShellRecipes::default(),
+            )
+            .unwrap()
+        };
+        let dry_run = solver().dry_run();
+        let solver = solver();
+        let meta = solver.meta.clone();
+        solver.run();
+
+        assert!(!meta.materials.conflict.is_empty());
+        assert_eq!(meta.bar_progress.load(Relaxed), dry_run.combination_count());
+        assert_eq!(dry_run.seconds_per_combination.len(), 5);
+        assert!(dry_run.seconds_per_combination.iter().all(|x| *x > 0.0));
+    }
+}
@@ -0,0 +1,111 @@
+//! Best combination when one of the materials of the best combination can't be used, e.g. when it is out of stock
+use crate::{
+    prelude::*,
+    result::{format_float, format_price, format_signed_price, get_best_wanted_index, ResultPool},
+};
+use colored::Colorize;
+use itertools::Itertools;
+use std::fmt;
+
+pub struct Fallback {
+    pub excluded: MaterialName,
+    /// `None` when the wanted gizmo can't be made without the material
+    pub line: Option<ResultLine>,
+}
+
+impl Fallback {
+    /// How much more the fallback costs than the best combination, infinite when there is no fallback
+    pub fn penalty(&self, best: &ResultLine) -> f64 {
+        self.line
+            .as_ref()
+            .map_or(f64::INFINITY, |x| x.price - best.price)
+    }
+}
+
+pub struct ExclusionSweep {
+    pub best: ResultLine,
+    /// Highest penalty first
+    pub fallbacks: Vec<Fallback>,
+}
+
+/// Find the best combination without each material of the best combination. Only looks in the pool of the solve, which
+/// holds every combination that can make the wanted gizmo, so nothing is solved again. Returns `None` when nothing was
+/// found.
+pub fn sweep(
+    best_per_level: &[Vec<ResultLine>],
+    pool: &ResultPool,
+    sort_type: SortType,
+) -> Option<ExclusionSweep> {
+    let best = best_per_level[get_best_wanted_index(best_per_level, sort_type)?][0].clone();
+    let fallbacks = best
+        .mat_combination
+        .iter()
+        .copied()
+        .unique()
+        .map(|excluded| fallback(pool, sort_type, excluded))
+        .sorted_by(|x, y| f64::total_cmp(&y.penalty(&best), &x.penalty(&best)))
+        .collect();
+    Some(ExclusionSweep { best, fallbacks })
+}
+
+/// Best combination in the pool that doesn't use the material
+pub fn fallback(pool: &ResultPool, sort_type: SortType, excluded: MaterialName) -> Fallback {
+    let without = pool.best_without(&[excluded]);
+    Fallback {
+        excluded,
+        line: get_best_wanted_index(&without, sort_type).map(|i| without[i][0].clone()),
+    }
+}
+
+impl fmt::Display for ExclusionSweep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", "Exclusion sweep".bright_green().underline())?;
+        writeln!(
+            f,
+            " Best at level {} for {}: {}\n",
+            self.best.level,
+            format_price(self.best.price),
+            MaterialName::vec_to_string(&self.best.mat_combination)
+        )?;
+
+        let width = self
+            .fallbacks
+            .iter()
+            .map(|x| x.excluded.to_string().chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("Without".len());
+        writeln!(
+            f,
+            " {:<width$}  {:>5}  {:>11}  {:>10}  {:>10}  Combination",
+            "Without", "Level", "Gizmo (%)", "Price", "Penalty"
+        )?;
+        for fallback in self.fallbacks.iter() {
+            let penalty = fallback.penalty(&self.best);
+            let penalty = if penalty > 0.0 {
+                format!("+{}", format_price(penalty))
+            } else {
+                format_signed_price(penalty)
+            };
+            match &fallback.line {
+                Some(line) => writeln!(
+                    f,
+                    " {:<width$}  {:>5}  {:>11}  {:>10}  {:>10}  {}",
+                    fallback.excluded.to_string(),
+                    line.level,
+                    format_float(line.prob_gizmo),
+                    format_price(line.price),
+                    penalty,
+                    MaterialName::vec_to_string(&line.mat_combination)
+                )?,
+                None => writeln!(
+                    f,
+                    " {:<width$}  {}",
+                    fallback.excluded.to_string(),
+                    "no combination can make the gizmo".red()
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
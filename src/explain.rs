@@ -0,0 +1,219 @@
+//! Trace of every step of the calculation of the gizmo probabilities of a material combination
+use crate::{
+    gizmo_cost_thresholds::find_gizmo_cost_thresholds,
+    jagex_sort,
+    perk_values::{calc_perk_rank_probabilities, get_perk_values, permutate_perk_ranks},
+    prelude::*,
+    result::format_float,
+};
+use colored::Colorize;
+use itertools::Itertools;
+use std::fmt;
+
+pub struct Explanation {
+    pub budget: Budget,
+    /// Base and rolls of each perk
+    pub perk_values: PartialPerkValuesVec,
+    /// Probability of each rank of each perk
+    pub rank_probabilities: PerkValuesVec,
+    pub combinations: Vec<CombinationTrace>,
+    /// The gizmo probabilities, the same as [`crate::calc_gizmo_probabilities`]
+    pub gizmos: Vec<Gizmo>,
+}
+
+pub struct CombinationTrace {
+    pub unsorted: RankCombination,
+    pub sorted: RankCombination,
+    /// Gizmo made when the budget roll is above its cost and at most the cost of the next one. The probability is the
+    /// contribution to the total probability of the gizmo.
+    pub thresholds: Vec<Gizmo>,
+}
+
+pub fn explain(
+    data: &Data,
+    budget: Budget,
+    input_materials: &Vec<MaterialName>,
+    gizmo_type: GizmoType,
+    is_ancient: bool,
+) -> Explanation {
+    let perk_values = get_perk_values(data, input_materials, gizmo_type, is_ancient);
+    let rank_probabilities = calc_perk_rank_probabilities(data, &perk_values, is_ancient);
+
+    let mut combinations = vec![];
+    let mut gizmos: Vec<Gizmo> = vec![];
+    for unsorted in permutate_perk_ranks(&rank_probabilities, None) {
+        let mut sorted = unsorted.clone();
+        jagex_sort::jagex_quicksort(&mut sorted);
+
+        let mut thresholds = find_gizmo_cost_thresholds(&sorted, budget.range.max);
+        crate::calc_probability_from_thresholds(&mut thresholds, &budget, sorted.probability);
+
+        for gizmo in thresholds.iter().filter(|x| x.probability != 0.0) {
+            if let Some(x) = gizmos.iter_mut().find(|x| **x == *gizmo) {
+                x.probability += gizmo.probability;
+            } else {
+                gizmos.push(*gizmo);
+            }
+        }
+        combinations.push(CombinationTrace {
+            unsorted,
+            sorted,
+            thresholds,
+        });
+    }
+    gizmos.sort_by(|x, y| f64::partial_cmp(&y.probability, &x.probability).unwrap());
+
+    Explanation {
+        budget,
+        perk_values,
+        rank_probabilities,
+        combinations,
+        gizmos,
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", "Perk values".bright_green().underline())?;
+        for perk in self.perk_values.iter() {
+            writeln!(
+                f,
+                " {:<20} base {:>4}, rolls [{}]",
+                perk.name.to_string(),
+                perk.base,
+                perk.rolls.iter().join(", ")
+            )?;
+        }
+
+        writeln!(f, "\n{}", "Rank probabilities".bright_green().underline())?;
+        for perk in self.rank_probabilities.iter() {
+            writeln!(f, " {}", perk.name.to_string().cyan())?;
+            for rank in perk.ranks.iter().take(perk.i_last + 1).skip(perk.i_first) {
+                writeln!(
+                    f,
+                    "   rank {} (cost {:>3}, threshold {:>3}): {}%",
+                    rank.values.rank,
+                    rank.values.cost,
+                    rank.values.threshold,
+                    format_float(rank.probability)
+                )?;
+            }
+        }
+
+        writeln!(
+            f,
+            "\n{} (budget {}..={})",
+            "Rank combinations".bright_green().underline(),
+            self.budget.range.min,
+            self.budget.range.max
+        )?;
+        for (i, comb) in self.combinations.iter().enumerate() {
+            writeln!(
+                f,
+                " {} probability {}%",
+                format!("#{}", i + 1).cyan(),
+                format_float(comb.unsorted.probability)
+            )?;
+            writeln!(f, "   before sort: {}", ranks_text(&comb.unsorted))?;
+            writeln!(f, "   after sort:  {}", ranks_text(&comb.sorted))?;
+            for (gizmo, next) in comb.thresholds.iter().zip(
+                comb.thresholds
+                    .iter()
+                    .skip(1)
+                    .map(|x| Some(x.cost))
+                    .chain([None]),
+            ) {
+                let next = next
+                    .unwrap_or(self.budget.range.max as i16)
+                    .min(self.budget.range.max as i16);
+                writeln!(
+                    f,
+                    "   budget {:>3}..={:<3}: {:<30} {}%",
+                    gizmo.cost + 1,
+                    next,
+                    gizmo_text(gizmo),
+                    format_float(gizmo.probability)
+                )?;
+            }
+        }
+
+        writeln!(f, "\n{}", "Gizmos".bright_green().underline())?;
+        for gizmo in self.gizmos.iter() {
+            writeln!(
+                f,
+                " {:<30} {}%",
+                gizmo_text(gizmo),
+                format_float(gizmo.probability)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn ranks_text(comb: &RankCombination) -> String {
+    comb.ranks
+        .iter()
+        .map(|x| format!("{} {} ({})", x.name, x.rank, x.cost))
+        .join(", ")
+}
+
+fn gizmo_text(gizmo: &Gizmo) -> String {
+    let perk = |x: Perk| {
+        if x.is_empty() {
+            String::from("Empty")
+        } else {
+            format!("{} {}", x.name, x.rank)
+        }
+    };
+    format!("{} + {}", perk(gizmo.perks.0), perk(gizmo.perks.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc_gizmo_probabilities;
+
+    #[test]
+    fn same_gizmos_as_the_solver() {
+        use MaterialName::*;
+        let data = Data::load();
+        let materials = vec![
+            PreciseComponents,
+            ArmadylComponents,
+            ArmadylComponents,
+            ConnectorParts,
+        ];
+        let expected = calc_gizmo_probabilities(
+            &data,
+            &Budget::create(120, false),
+            &materials,
+            GizmoType::Weapon,
+            false,
+        );
+        let explanation = explain(
+            &data,
+            Budget::create(120, false),
+            &materials,
+            GizmoType::Weapon,
+            false,
+        );
+
+        assert_eq!(explanation.gizmos.len(), expected.len());
+        for (x, y) in explanation.gizmos.iter().zip(expected.iter()) {
+            assert!(x == y);
+            assert_eq!(x.probability, y.probability);
+        }
+
+        let total: f64 = explanation
+            .combinations
+            .iter()
+            .flat_map(|x| x.thresholds.iter())
+            .map(|x| x.probability)
+            .sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        assert!(explanation
+            .combinations
+            .iter()
+            .all(|x| x.unsorted.probability == x.sorted.probability));
+    }
+}
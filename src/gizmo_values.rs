@@ -0,0 +1,213 @@
+use crate::prelude::*;
+use colored::Colorize;
+use serde::{ser::SerializeStruct, Serialize};
+use std::{fs, str::FromStr};
+
+/// Value in coins of the gizmos with a perk, or with a pair of perks
+#[derive(Debug, Clone, PartialEq)]
+pub struct GizmoValue {
+    pub gizmo: Gizmo,
+    /// The second perk of the gizmo doesn't matter when only one perk is given
+    pub any_second: bool,
+    pub value: f64,
+}
+
+impl GizmoValue {
+    pub fn matches(&self, gizmo: &Gizmo) -> bool {
+        if self.any_second {
+            gizmo.contains(&self.gizmo)
+        } else {
+            gizmo.same(&self.gizmo)
+        }
+    }
+
+    /// Parse a value of the form `<perk> <rank>[ + <perk> <rank>]=<coins>`, e.g. `precise 5 + equilibrium 2=8e6`.
+    /// Use `<perk> <rank> + empty` to only match gizmos without a second perk.
+    pub fn parse(text: &str) -> Result<GizmoValue, String> {
+        let (gizmo, value) = text.rsplit_once('=').ok_or(format!(
+            "Gizmo value '{}' is not of the form perk rank=coins",
+            text.yellow()
+        ))?;
+        let value = value
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid value '{}' for {}", value.trim().yellow(), gizmo))?;
+
+        let (gizmo, any_second) = parse_gizmo(gizmo)?;
+        Ok(GizmoValue {
+            gizmo,
+            any_second,
+            value,
+        })
+    }
+}
+
+/// Parse a gizmo of the form `<perk> <rank>[ + <perk> <rank>]`. Also returns whether the second perk doesn't matter,
+/// which is the case when only one perk is given.
+pub fn parse_gizmo(text: &str) -> Result<(Gizmo, bool), String> {
+    let mut perks = text.split('+').map(parse_perk);
+    let first = perks.next().unwrap()?;
+    let second = perks.next().transpose()?;
+    if perks.next().is_some() {
+        return Err(format!(
+            "Gizmo '{}' has more than two perks",
+            text.trim().yellow()
+        ));
+    }
+    if first.is_empty() {
+        return Err(format!(
+            "First perk of gizmo '{}' can't be empty",
+            text.trim().yellow()
+        ));
+    }
+
+    Ok((
+        Gizmo {
+            perks: (first, second.unwrap_or_default()),
+            ..Default::default()
+        },
+        second.is_none(),
+    ))
+}
+
+impl Serialize for GizmoValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut state = serializer.serialize_struct("GizmoValue", 3)?;
+        state.serialize_field("perks", &[self.gizmo.perks.0, self.gizmo.perks.1])?;
+        state.serialize_field("any_second", &self.any_second)?;
+        state.serialize_field("value", &self.value)?;
+        state.end()
+    }
+}
+
+/// The gizmos that are worth something besides the wanted one
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct GizmoValues {
+    values: Vec<GizmoValue>,
+}
+
+impl GizmoValues {
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, GizmoValue> {
+        self.values.iter()
+    }
+
+    /// Value of a gizmo. When several values match the highest one is used, gizmos without a value are worth nothing.
+    pub fn value(&self, gizmo: &Gizmo) -> f64 {
+        self.values
+            .iter()
+            .filter(|x| x.matches(gizmo))
+            .map(|x| x.value)
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Load the values from the file, one per line with `#` for comments, followed by the ones given on the command line
+pub fn load_gizmo_values(
+    file_path: &Option<String>,
+    entries: &[String],
+) -> Result<GizmoValues, String> {
+    let mut values = vec![];
+    if let Some(file_path) = file_path {
+        let text = fs::read_to_string(file_path)
+            .map_err(|err| format!("Failed to read {}: {}", file_path, err))?;
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            values.push(GizmoValue::parse(line).map_err(|err| format!("{err} in {file_path}"))?);
+        }
+    }
+    for entry in entries {
+        values.push(GizmoValue::parse(entry)?);
+    }
+    Ok(GizmoValues { values })
+}
+
+fn parse_perk(text: &str) -> Result<Perk, String> {
+    let text = text.trim();
+    if text.eq_ignore_ascii_case("empty") {
+        return Ok(Perk::default());
+    }
+    let (name, rank) = text.rsplit_once(' ').ok_or(format!(
+        "Perk '{}' is not of the form perk rank",
+        text.yellow()
+    ))?;
+    let name = PerkName::from_str(name.trim())
+        .map_err(|_| format!("Perk '{}' does not exist.", name.trim().yellow()))?;
+    let rank = rank
+        .parse()
+        .map_err(|_| format!("Invalid rank '{}' for {}", rank.yellow(), name))?;
+    Ok(Perk { name, rank })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gizmo(first: (PerkName, u8), second: (PerkName, u8)) -> Gizmo {
+        Gizmo {
+            perks: (
+                Perk {
+                    name: first.0,
+                    rank: first.1,
+                },
+                Perk {
+                    name: second.0,
+                    rank: second.1,
+                },
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn single_perk_matches_any_second_perk() {
+        let value = GizmoValue::parse("precise 5=1e6").unwrap();
+        assert_eq!(value.value, 1e6);
+        assert!(value.matches(&gizmo((PerkName::Precise, 5), (PerkName::Empty, 0))));
+        assert!(value.matches(&gizmo((PerkName::Biting, 2), (PerkName::Precise, 5))));
+        assert!(!value.matches(&gizmo((PerkName::Precise, 4), (PerkName::Empty, 0))));
+
+        let only = GizmoValue::parse("Precise 5 + empty = 1e6").unwrap();
+        assert!(only.matches(&gizmo((PerkName::Precise, 5), (PerkName::Empty, 0))));
+        assert!(!only.matches(&gizmo((PerkName::Precise, 5), (PerkName::Biting, 2))));
+    }
+
+    #[test]
+    fn highest_matching_value_is_used() {
+        let values = GizmoValues {
+            values: vec![
+                GizmoValue::parse("precise 5=1000").unwrap(),
+                GizmoValue::parse("biting 2 + precise 5=5000").unwrap(),
+            ],
+        };
+        let both = gizmo((PerkName::Precise, 5), (PerkName::Biting, 2));
+        assert_eq!(values.value(&both), 5000.0);
+        assert_eq!(
+            values.value(&gizmo((PerkName::Precise, 5), (PerkName::Empty, 0))),
+            1000.0
+        );
+        assert_eq!(
+            values.value(&gizmo((PerkName::Biting, 2), (PerkName::Empty, 0))),
+            0.0
+        );
+    }
+
+    #[test]
+    fn rejects_bad_values() {
+        assert!(GizmoValue::parse("precise 5").is_err());
+        assert!(GizmoValue::parse("precise=100").is_err());
+        assert!(GizmoValue::parse("notaperk 1=100").is_err());
+        assert!(GizmoValue::parse("precise 5=lots").is_err());
+        assert!(GizmoValue::parse("empty=100").is_err());
+        assert!(GizmoValue::parse("precise 1 + biting 1 + blunted 1=100").is_err());
+    }
+}
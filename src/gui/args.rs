@@ -9,6 +9,7 @@ pub fn ArgsForm<'a>(
     cx: Scope,
     on_submit: EventHandler<'a, FormEvent>,
     is_running: bool,
+    estimate: Option<String>,
 ) -> Element<'a> {
     cx.render(rsx!(
         form {
@@ -78,7 +79,7 @@ pub fn ArgsForm<'a>(
                     tr {
                         th { "Sort on:" }
                     }
-                    for x in [("Lowest price", "Price"), ("Best gizmo chance", "Gizmo"), ("Best attempt chance", "Attempt")] {
+                    for x in [("Lowest price", "Price"), ("Best gizmo chance", "Gizmo"), ("Best attempt chance", "Attempt"), ("Custom objective", "Custom"), ("Best chance within budget", "Budget"), ("Best expected value", "Value")] {
                         tr {
                             td {
                                 input {
@@ -104,6 +105,36 @@ pub fn ArgsForm<'a>(
                             input { r#type: "number", name: "alt count", min: "0", max: "254", value: "5" }
                         }
                     }
+                    tr {
+                        th {
+                            class: "help",
+                            title: "Expression to rank on when sorting on a custom objective, e.g. price + 2e6 * distinct_rare. Prefix with max: to maximize it.",
+                            "Objective:"
+                        }
+                        td {
+                            input { r#type: "text", name: "objective", placeholder: "e.g.: price / prob_gizmo" }
+                        }
+                    }
+                    tr {
+                        th {
+                            class: "help",
+                            title: "Coins available when sorting on the best chance within budget.",
+                            "Budget:"
+                        }
+                        td {
+                            input { r#type: "number", name: "budget", min: "1", placeholder: "Coins" }
+                        }
+                    }
+                    tr {
+                        th {
+                            class: "help",
+                            title: "Semicolon separated list of gizmos and their value in coins when sorting on the best expected value. Only material combinations that can make the wanted gizmo are ranked.",
+                            "Gizmo values:"
+                        }
+                        td {
+                            input { r#type: "text", name: "gizmo values", placeholder: "e.g.: precise 4=2e6; precise 5=8e6" }
+                        }
+                    }
                     tr {
                         th {
                             class: "help",
@@ -124,8 +155,53 @@ pub fn ArgsForm<'a>(
                             input { r#type: "checkbox", name: "limit CPU", checked: "false" }
                         }
                     }
+                    tr {
+                        th {
+                            class: "help",
+                            title: "Collect the results for which no other result is better on price, probability and amount of distinct and rare materials at once, shown in the Pareto front tab.",
+                            "Pareto front:"
+                        }
+                        td {
+                            input { r#type: "checkbox", name: "pareto", checked: "false" }
+                        }
+                    }
+                    tr {
+                        th {
+                            class: "help",
+                            title: "Keep every result of the search so the ranking follows price edits in the prices tab. Uses a lot of memory for large searches.",
+                            "Re-rank on price edits:"
+                        }
+                        td {
+                            input { r#type: "checkbox", name: "rerank", checked: "false" }
+                        }
+                    }
+                    tr {
+                        th {
+                            class: "help",
+                            title: "Only estimate the amount of combinations and how long the search takes on this machine.",
+                            "Dry run:"
+                        }
+                        td {
+                            input { r#type: "checkbox", name: "dry run", checked: "false" }
+                        }
+                    }
+                    tr {
+                        th {
+                            class: "help",
+                            title: "With a dry run, list the materials to exclude so the search takes less than this many minutes.",
+                            "Time target:"
+                        }
+                        td {
+                            input { r#type: "number", name: "time target", min: "0", step: "any", placeholder: "Minutes" }
+                        }
+                    }
                 }
             }
+            if let Some(estimate) = estimate {
+                rsx!(
+                    div { class: "estimate", "{estimate}" }
+                )
+            }
             if *is_running {
                 rsx!(
                     button { r#type: "submit", value: "Submit", class: "btn btn-danger" , "Cancel" }
@@ -175,15 +251,40 @@ pub fn form_to_args(values: &HashMap<String, String>) -> Result<Args, String> {
             .ok_or(String::from("Please select a sort type"))?,
         true,
     )?;
+    let budget = match values.get("budget").filter(|x| !x.is_empty()) {
+        Some(x) if sort_type == SortType::Budget => Some(
+            x.parse()
+                .map_err(|_| String::from("Please enter a valid budget"))?,
+        ),
+        _ => None,
+    };
+    let gizmo_value = match values.get("gizmo values") {
+        Some(x) if sort_type == SortType::Value => x
+            .split(';')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect(),
+        _ => vec![],
+    };
+    let time_target = values
+        .get("time target")
+        .filter(|x| !x.is_empty())
+        .map(|x| {
+            x.parse()
+                .map_err(|_| String::from("Please enter a valid time target"))
+        })
+        .transpose()?;
     let alt_count = values
         .get("alt count")
         .map(|x| x.parse().unwrap_or(0))
         .unwrap_or(0);
 
     let cli = Cli {
-        ancient: values.get("ancient").unwrap() == "true",
-        gizmo_type,
+        ancient: Some(values.get("ancient").unwrap() == "true"),
+        gizmo_type: Some(gizmo_type),
         invention_level,
+        format: OutputFormat::Text,
         command: Commands::Gizmo {
             perk: values.get("perk one").unwrap().clone(),
             rank,
@@ -192,11 +293,42 @@ pub fn form_to_args(values: &HashMap<String, String>) -> Result<Args, String> {
             fuzzy: false,
             exclude,
             sort_type,
+            objective: values
+                .get("objective")
+                .filter(|x| sort_type == SortType::Custom && !x.trim().is_empty())
+                .cloned(),
+            budget,
+            gizmo_value,
+            gizmo_value_file: None,
             out_file: String::from("false"),
             price_file: Args::default().price_file.unwrap_or(String::from("false")),
+            price_override_file: Args::default()
+                .price_override_file
+                .unwrap_or(String::from("false")),
+            price: vec![],
+            price_max_age: Args::default().price_max_age.unwrap_or(0),
+            price_provider: Args::default().price_provider,
+            price_endpoint: None,
+            disassembly_file: None,
+            prefer_disassembly: false,
+            shell_recipe_file: Args::default()
+                .shell_recipe_file
+                .unwrap_or(String::from("false")),
+            strict_prices: false,
+            sensitivity: None,
+            monte_carlo: 0,
+            pareto: values.get("pareto").is_some_and(|x| x == "true"),
+            calc_links: false,
+            exclusion_sweep: false,
+            conflicts: false,
+            neighbours: None,
+            dry_run: values.get("dry run").is_some_and(|x| x == "true"),
+            time_target,
             alt_count,
             limit_cpu: values.get("limit CPU").unwrap() == "true",
         },
     };
-    Args::create(&cli)
+    let mut args = Args::create(&cli)?;
+    args.keep_pool |= values.get("rerank").is_some_and(|x| x == "true");
+    Ok(args)
 }
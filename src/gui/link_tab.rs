@@ -0,0 +1,112 @@
+use crate::wiki::WikiImage;
+use dioxus::prelude::*;
+use perk_solver::{
+    calc_gizmo_probabilities, prelude::*, recipe_code::RecipeCode, result::format_float, wiki,
+};
+
+pub fn LinkTab<'a>(cx: Scope<'a>, url: &'a UseState<String>) -> Element<'a> {
+    let decoded = if url.get().trim().is_empty() {
+        None
+    } else {
+        Some(parse_recipe(url.get()).map(|link| {
+            let budget = Budget::create(link.level as usize, link.ancient);
+            let gizmos = calc_gizmo_probabilities(
+                &Data::load(),
+                &budget,
+                &link.materials,
+                link.gizmo_type,
+                link.ancient,
+            );
+            (link, gizmos)
+        }))
+    };
+
+    cx.render(rsx!(
+        div { "Paste a link to the perk calculator of the wiki or a recipe code to see all gizmos its materials can make." }
+        input {
+            r#type: "text",
+            class: "calc-link-input",
+            placeholder: "https://runescape.wiki/w/Calculator:Perks#g=... or ps1:...",
+            value: "{url}",
+            oninput: move |ev| url.set(ev.value.clone()),
+        }
+        if let Some(Err(err)) = &decoded {
+            rsx!(
+                div {
+                    class: "error",
+                    b { "Error: " },
+                    err.clone()
+                }
+            )
+        }
+        if let Some(Ok((link, gizmos))) = &decoded {
+            rsx!(
+                div {
+                    class: "materials",
+                    b { gizmo_text(link) }
+                    span {
+                        for (i, mat) in link.materials.iter().enumerate() {
+                            if i > 0 {
+                                rsx!(", ")
+                            }
+                            WikiImage(cx, mat.to_str())
+                            "{mat}"
+                        }
+                    }
+                }
+                table {
+                    class: "wikitable align-left-1 align-left-2",
+                    tr {
+                        th { "Perk one" }
+                        th { "Perk two" }
+                        th { "Probability (%)" }
+                    }
+                    for gizmo in gizmos.iter() {
+                        tr {
+                            td { perk_text(gizmo.perks.0) }
+                            td { perk_text(gizmo.perks.1) }
+                            td { format_float(gizmo.probability) }
+                        }
+                    }
+                }
+            )
+        }
+    ))
+}
+
+/// A recipe code, or a calculator link when there is a `#`
+fn parse_recipe(text: &str) -> Result<wiki::CalcLink, String> {
+    if text.contains('#') {
+        return wiki::parse_wiki_calc_link(text);
+    }
+    let code = RecipeCode::parse(text)?;
+    match code.level {
+        InventionLevel::Single(level) if !code.materials.is_empty() => Ok(wiki::CalcLink {
+            gizmo_type: code.gizmo_type,
+            level,
+            ancient: code.ancient,
+            materials: code.materials,
+        }),
+        InventionLevel::Single(_) => Err(String::from("The recipe code has no materials")),
+        InventionLevel::Range(..) => Err(String::from(
+            "The recipe code has a level range instead of a single level",
+        )),
+    }
+}
+
+fn gizmo_text(link: &wiki::CalcLink) -> String {
+    format!(
+        "{}{} gizmo, level {}: ",
+        if link.ancient { "Ancient " } else { "" },
+        link.gizmo_type,
+        link.level
+    )
+}
+
+fn perk_text(perk: Perk) -> String {
+    if perk.is_empty() {
+        String::new()
+    } else {
+        format!("{} {}", perk.name, perk.rank)
+    }
+}
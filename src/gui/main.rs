@@ -1,11 +1,12 @@
 #![allow(non_snake_case)]
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // Prevents opening a console window alongside the gui
 mod args;
+mod link_tab;
 mod prices_tab;
 mod result;
 mod wiki;
 
-use std::sync::atomic::Ordering;
+use std::sync::{atomic::Ordering, Arc};
 use std::time::Duration;
 
 use dioxus::prelude::*;
@@ -14,7 +15,12 @@ use dioxus_desktop::{Config, WindowBuilder};
 
 use indicatif::HumanCount;
 use itertools::Itertools;
-use perk_solver::{component_prices, prelude::*, Solver, SolverMetadata};
+use perk_solver::{
+    component_prices,
+    prelude::*,
+    result::{ParetoFront, ResultPool},
+    Solver, SolverMetadata,
+};
 use strum::{EnumIter, IntoEnumIterator, IntoStaticStr};
 use strum_macros::EnumVariantNames;
 use tokio::time;
@@ -44,6 +50,13 @@ fn App(cx: Scope) -> Element {
     let end_time = use_state(cx, || None);
     let tab_selection = use_state(cx, || TabSelection::Result);
     let prices_status = use_state(cx, || None::<Result<component_prices::PriceSource, String>>);
+    let prices = use_ref(cx, component_prices::PriceMap::new);
+    let price_issues = use_state(cx, Vec::<component_prices::PriceIssue>::new);
+    let shells = use_ref(cx, component_prices::shells::ShellRecipes::default);
+    let pool = use_ref(cx, || None::<Arc<ResultPool>>);
+    let pareto_front = use_ref(cx, || None::<ParetoFront>);
+    let import_recipe = use_state(cx, String::new);
+    let estimate = use_state(cx, || None::<String>);
 
     let on_submit = move |ev: FormEvent| {
         if solver.read().is_some() && result.read().is_none() {
@@ -55,51 +68,80 @@ fn App(cx: Scope) -> Element {
                 .store(true, Ordering::Relaxed);
         } else {
             *result.write() = None;
+            *pool.write() = None;
+            *pareto_front.write() = None;
             *solver.write() = None;
             start_time.set(None);
             end_time.set(None);
             error.set(None);
+            estimate.set(None);
 
-            match args::form_to_args(&ev.values) {
-                Ok(args) => match Solver::new(args, Data::load()) {
-                    Ok(s) => {
-                        *solver.write() = Some(s.meta.clone());
-                        progress.set(0);
-                        start_time.set(Some(time::Instant::now()));
+            let args = match prices_status.get() {
+                Some(Ok(_)) => args::form_to_args(&ev.values),
+                Some(Err(err)) => Err(format!("Component prices are not available: {err}")),
+                None => Err(String::from("Component prices are still being loaded.")),
+            };
 
-                        cx.spawn({
-                            to_owned![result, end_time];
+            match args {
+                Ok(args) if args.dry_run => {
+                    match Solver::new(args, Data::load(), prices.read().clone(), &shells.read()) {
+                        Ok(s) => cx.spawn({
+                            to_owned![estimate];
                             async move {
-                                let res = tokio::task::spawn_blocking(move || s.run()).await;
-                                *result.write() = Some(res.unwrap());
-                                end_time.set(Some(time::Instant::now()));
+                                let res =
+                                    tokio::task::spawn_blocking(move || s.dry_run().to_string())
+                                        .await;
+                                estimate.set(res.ok());
                             }
-                        });
+                        }),
+                        Err(err) => error.set(Some(err)),
+                    }
+                }
+                Ok(args) => {
+                    match Solver::new(args, Data::load(), prices.read().clone(), &shells.read()) {
+                        Ok(s) => {
+                            *solver.write() = Some(s.meta.clone());
+                            progress.set(0);
+                            start_time.set(Some(time::Instant::now()));
 
-                        cx.spawn({
-                            to_owned![progress, solver, result];
-                            async move {
-                                let mut interval = time::interval(Duration::from_millis(200));
-                                loop {
-                                    interval.tick().await;
-                                    if let Some(solver) = solver.read().as_ref() {
-                                        let val = solver.bar_progress.load(Ordering::Relaxed);
-                                        progress.set(val);
-                                        if val == solver.total_combination_count {
+                            cx.spawn({
+                                to_owned![result, end_time, pool, pareto_front];
+                                async move {
+                                    let res =
+                                        tokio::task::spawn_blocking(move || s.run_detailed()).await;
+                                    let output = res.unwrap();
+                                    *pool.write() = output.pool.map(Arc::new);
+                                    *pareto_front.write() = output.pareto_front;
+                                    *result.write() = Some(output.best_per_level);
+                                    end_time.set(Some(time::Instant::now()));
+                                }
+                            });
+
+                            cx.spawn({
+                                to_owned![progress, solver, result];
+                                async move {
+                                    let mut interval = time::interval(Duration::from_millis(200));
+                                    loop {
+                                        interval.tick().await;
+                                        if let Some(solver) = solver.read().as_ref() {
+                                            let val = solver.bar_progress.load(Ordering::Relaxed);
+                                            progress.set(val);
+                                            if val == solver.total_combination_count {
+                                                break;
+                                            }
+                                        }
+                                        if result.read().is_some() {
                                             break;
                                         }
                                     }
-                                    if result.read().is_some() {
-                                        break;
-                                    }
                                 }
-                            }
-                        })
-                    }
-                    Err(err) => {
-                        error.set(Some(err));
+                            })
+                        }
+                        Err(err) => {
+                            error.set(Some(err));
+                        }
                     }
-                },
+                }
                 Err(err) => {
                     error.set(Some(err));
                 }
@@ -107,16 +149,24 @@ fn App(cx: Scope) -> Element {
         }
     };
 
-    use_future(cx, prices_status, |prices_status| async move {
-        if prices_status.get().is_none() {
-            let res = tokio::task::spawn_blocking(|| {
-                perk_solver::component_prices::load_component_prices(
-                    &Args::default().price_file,
-                    true,
-                )
-            })
-            .await;
-            prices_status.set(Some(res.unwrap()));
+    use_future(cx, prices_status, |prices_status| {
+        to_owned![prices, price_issues, shells];
+        async move {
+            if prices_status.get().is_none() {
+                let res = tokio::task::spawn_blocking(|| {
+                    component_prices::load_layered_prices(&Args::default(), true)
+                })
+                .await;
+                match res.unwrap() {
+                    Ok(loaded) => {
+                        *prices.write() = loaded.prices;
+                        price_issues.set(loaded.issues);
+                        *shells.write() = loaded.shells;
+                        prices_status.set(Some(Ok(loaded.source)));
+                    }
+                    Err(err) => prices_status.set(Some(Err(err))),
+                }
+            }
         }
     });
 
@@ -124,7 +174,8 @@ fn App(cx: Scope) -> Element {
         style { include_str!("./css/common.css") },
         args::ArgsForm {
             on_submit: on_submit,
-            is_running: solver.read().is_some() && result.read().is_none()
+            is_running: solver.read().is_some() && result.read().is_none(),
+            estimate: estimate.get().clone()
         },
         div {
             class: "tabber",
@@ -175,8 +226,18 @@ fn App(cx: Scope) -> Element {
                         rsx!(result::FullResultTable(cx, result))
                     }
                 ),
+                TabSelection::ParetoFront => rsx!(
+                    if let Some(front) = pareto_front.read().as_ref() {
+                        rsx!(result::ParetoTable(cx, front))
+                    } else {
+                        rsx!("Check \"Pareto front\" before solving to collect it.")
+                    }
+                ),
+                TabSelection::ImportRecipe => rsx!(
+                    link_tab::LinkTab(cx, import_recipe)
+                ),
                 TabSelection::Prices => rsx!(
-                    prices_tab::PricesTab(cx, &prices_status)
+                    prices_tab::PricesTab(cx, &prices_status, prices, price_issues, shells, result, pool, pareto_front)
                 )
             }
         }
@@ -188,6 +249,10 @@ enum TabSelection {
     Result,
     #[strum(serialize = "Full result")]
     FullResult,
+    #[strum(serialize = "Pareto front")]
+    ParetoFront,
+    #[strum(serialize = "Import recipe")]
+    ImportRecipe,
     Prices,
 }
 
@@ -1,13 +1,34 @@
 use crate::wiki;
 use dioxus::prelude::*;
-use perk_solver::{component_prices, prelude::*};
+use perk_solver::{
+    component_prices,
+    cost_model::DefaultCostModel,
+    prelude::*,
+    result::{ParetoFront, ResultPool},
+};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Time without price edits before the results are ranked again
+const RERANK_DELAY: Duration = Duration::from_millis(300);
+/// Counts the price edits, a re-rank only runs when no edit came after the one that started it
+static PRICE_EDITS: AtomicU64 = AtomicU64::new(0);
 
 pub fn PricesTab<'a>(
     cx: Scope<'a>,
     prices_status: &'a UseState<Option<Result<component_prices::PriceSource, String>>>,
+    prices: &'a UseRef<component_prices::PriceMap>,
+    price_issues: &'a UseState<Vec<component_prices::PriceIssue>>,
+    shells: &'a UseRef<component_prices::shells::ShellRecipes>,
+    result: &'a UseRef<Option<Vec<Vec<ResultLine>>>>,
+    pool: &'a UseRef<Option<Arc<ResultPool>>>,
+    pareto_front: &'a UseRef<Option<ParetoFront>>,
 ) -> Element<'a> {
-    let shell_price_update_check = use_state(cx, || ());
-
     cx.render(rsx!(
         if let Some(status) = prices_status.get() {
             rsx!(
@@ -16,7 +37,6 @@ pub fn PricesTab<'a>(
                     class: "btn btn-primary",
                     float: "right",
                     onclick: move |_| {
-                        *perk_solver::component_prices::PRICES.write().unwrap() = None;
                         prices_status.set(None);
                     },
                     "Reload"
@@ -28,12 +48,19 @@ pub fn PricesTab<'a>(
                             if *source == component_prices::PriceSource::Local {
                                 rsx!("Failed to load prices from Runescape.wiki, falling back to local price file.")
                             }
+                            for issue in price_issues.get().iter() {
+                                div {
+                                    class: "warning",
+                                    b { "Warning: " },
+                                    "{issue}"
+                                }
+                            }
                             h3 { "Common materials" }
                             div {
                                 class: "prices-container",
                                 table {
                                     for mat in COMMON_MATERIALS.iter() {
-                                        PriceTabElement(cx, *mat, &shell_price_update_check)
+                                        PriceTabElement(cx, *mat, prices, shells, result, pool, pareto_front)
                                     }
                                 }
                             }
@@ -42,7 +69,7 @@ pub fn PricesTab<'a>(
                                 class: "prices-container",
                                 table {
                                     for mat in UNCOMMON_MATERIALS.iter() {
-                                        PriceTabElement(cx, *mat, &shell_price_update_check)
+                                        PriceTabElement(cx, *mat, prices, shells, result, pool, pareto_front)
                                     }
                                 }
                             }
@@ -51,12 +78,12 @@ pub fn PricesTab<'a>(
                                 class: "prices-container",
                                 table {
                                     for mat in RARE_MATERIALS.iter() {
-                                        PriceTabElement(cx, *mat, &shell_price_update_check)
+                                        PriceTabElement(cx, *mat, prices, shells, result, pool, pareto_front)
                                     }
                                 }
                             }
                             h3 { "Gizmo shells" }
-                            div { "Prices are calculated based on the material prices above." }
+                            div { "Prices are calculated based on the material prices above, unless the shell recipe file gives a fixed price." }
                             table {
                                 class: "wikitable align-left-1",
                                 tr {
@@ -64,18 +91,9 @@ pub fn PricesTab<'a>(
                                     th { "Price" }
                                     th { "Source materials" }
                                 }
-                                PriceTabShellElement(cx, GizmoType::Weapon, false,
-                                    &[(10, MaterialName::BladeParts), (5, MaterialName::CraftedParts), (2, MaterialName::StrongComponents)])
-                                PriceTabShellElement(cx, GizmoType::Armour, false,
-                                    &[(10, MaterialName::DeflectingParts), (5, MaterialName::CraftedParts), (2, MaterialName::ProtectiveComponents)])
-                                PriceTabShellElement(cx, GizmoType::Tool, false,
-                                    &[(10, MaterialName::HeadParts), (5, MaterialName::CraftedParts), (2, MaterialName::PreciseComponents)])
-                                PriceTabShellElement(cx, GizmoType::Weapon, true,
-                                    &[(20, MaterialName::BladeParts), (20, MaterialName::HistoricComponents), (2, MaterialName::ClassicComponents), (2, MaterialName::StrongComponents)])
-                                PriceTabShellElement(cx, GizmoType::Armour, true,
-                                    &[(20, MaterialName::DeflectingParts), (20, MaterialName::HistoricComponents), (2, MaterialName::ClassicComponents), (2, MaterialName::ProtectiveComponents)])
-                                PriceTabShellElement(cx, GizmoType::Tool, true,
-                                    &[(20, MaterialName::HeadParts), (20, MaterialName::HistoricComponents), (2, MaterialName::ClassicComponents), (2, MaterialName::PreciseComponents)])
+                                for shell in shells.read().iter() {
+                                    PriceTabShellElement(cx, prices, shell.clone())
+                                }
                             }
                         )
                     }
@@ -89,15 +107,11 @@ pub fn PricesTab<'a>(
 
 fn PriceTabShellElement<'a>(
     cx: Scope<'a>,
-    shell: GizmoType,
-    ancient: bool,
-    comb: &[(u8, MaterialName)],
+    prices: &'a UseRef<component_prices::PriceMap>,
+    shell: component_prices::shells::ShellRecipe,
 ) -> Element<'a> {
-    let name = if ancient {
-        format!("Ancient {} gizmo shell", shell.to_string().to_lowercase())
-    } else {
-        format!("{} gizmo shell", shell.to_string())
-    };
+    let name = shell.name();
+    let price = shell.price(&prices.read());
 
     cx.render(rsx!(
         tr {
@@ -105,24 +119,75 @@ fn PriceTabShellElement<'a>(
                 wiki::WikiImage(cx, name.as_str())
                 "{name}"
             }
-            td { format!("{:.0}", perk_solver::component_prices::get_shell_price(shell, ancient)) }
+            td { format!("{:.0}", price) }
             td {
-                for (i, (n, mat)) in comb.iter().enumerate() {
-                    if i > 0 {
-                        rsx!(", ")
-                    }
-                    "{n} × "
-                    wiki::WikiImage(cx, mat.to_str())
+                match &shell.cost {
+                    component_prices::shells::ShellCost::Recipe(recipe) => rsx!(
+                        for (i, (n, mat)) in recipe.iter().enumerate() {
+                            if i > 0 {
+                                rsx!(", ")
+                            }
+                            "{n} × "
+                            wiki::WikiImage(cx, mat.to_str())
+                        }
+                    ),
+                    component_prices::shells::ShellCost::Fixed(_) => rsx!("Fixed price"),
                 }
             }
         }
     ))
 }
 
+/// Rank the results of the last solve again with the current prices, on another thread as the pool can be large
+async fn rerank(
+    prices: UseRef<component_prices::PriceMap>,
+    shells: UseRef<component_prices::shells::ShellRecipes>,
+    result: UseRef<Option<Vec<Vec<ResultLine>>>>,
+    pool: UseRef<Option<Arc<ResultPool>>>,
+    pareto_front: UseRef<Option<ParetoFront>>,
+) {
+    let solved = match pool.read().as_ref() {
+        Some(x) => x.clone(),
+        None => return,
+    };
+    let args = solved.args();
+    let cost_model = DefaultCostModel::new(
+        prices.read().clone(),
+        shells.read().get(args.gizmo_type, args.ancient),
+    );
+    let res = tokio::task::spawn_blocking({
+        let solved = solved.clone();
+        move || {
+            let front = solved
+                .args()
+                .pareto
+                .then(|| solved.pareto_front(&cost_model));
+            (solved.rerank(&cost_model), front)
+        }
+    })
+    .await;
+
+    // Skip the result when a new solve replaced the pool in the meantime
+    let is_current = pool
+        .read()
+        .as_ref()
+        .is_some_and(|x| Arc::ptr_eq(x, &solved));
+    if let (Ok((ranked, front)), true) = (res, is_current) {
+        *result.write() = Some(ranked);
+        if front.is_some() {
+            *pareto_front.write() = front;
+        }
+    }
+}
+
 fn PriceTabElement<'a>(
     cx: Scope<'a>,
     mat: MaterialName,
-    update_check: &'a UseState<()>,
+    prices: &'a UseRef<component_prices::PriceMap>,
+    shells: &'a UseRef<component_prices::shells::ShellRecipes>,
+    result: &'a UseRef<Option<Vec<Vec<ResultLine>>>>,
+    pool: &'a UseRef<Option<Arc<ResultPool>>>,
+    pareto_front: &'a UseRef<Option<ParetoFront>>,
 ) -> Element<'a> {
     cx.render(rsx!(
         tr {
@@ -134,15 +199,27 @@ fn PriceTabElement<'a>(
                 input {
                     r#type: "number",
                     min: "0",
-                    value: perk_solver::component_prices::PRICES.read().unwrap().as_ref().unwrap().get(mat),
+                    value: prices.read().get(mat),
                     oninput: move |ev| {
-                        if let Some(prices) = perk_solver::component_prices::PRICES.write().unwrap().as_mut() {
-                            prices.set(mat, ev.value.parse().unwrap_or(0.0));
-                            update_check.needs_update();
-                        }
+                        prices.write().set(mat, ev.value.parse().unwrap_or(0.0));
+                        let edit = PRICE_EDITS.fetch_add(1, Ordering::Relaxed) + 1;
+                        cx.spawn({
+                            to_owned![prices, shells, result, pool, pareto_front];
+                            async move {
+                                tokio::time::sleep(RERANK_DELAY).await;
+                                if PRICE_EDITS.load(Ordering::Relaxed) == edit {
+                                    rerank(prices, shells, result, pool, pareto_front).await;
+                                }
+                            }
+                        });
                     }
                 }
             }
+            td {
+                class: "price-source",
+                title: "Where this price came from",
+                "{prices.read().source(mat)}"
+            }
         }
     ))
 }
@@ -1,7 +1,9 @@
-use crate::wiki::{make_wiki_calc_link, WikiImage};
+use crate::wiki::WikiImage;
 use dioxus::prelude::*;
 use itertools::Itertools;
 use perk_solver::prelude::*;
+use perk_solver::recipe_code::RecipeCode;
+use perk_solver::wiki::make_wiki_calc_link;
 
 pub fn FullResultTable<'a>(cx: Scope<'a>, result: &Vec<Vec<ResultLine>>) -> Element<'a> {
     cx.render(rsx!(
@@ -44,20 +46,51 @@ pub fn FullResultTable<'a>(cx: Scope<'a>, result: &Vec<Vec<ResultLine>>) -> Elem
     ))
 }
 
+pub fn ParetoTable<'a>(cx: Scope<'a>, front: &perk_solver::result::ParetoFront) -> Element<'a> {
+    cx.render(rsx!(
+        div { "Combinations for which no other combination is at least as good on every column at once." }
+        table {
+            class: "wikitable align-left-7",
+            tr {
+                th { rowspan: 2, "Level" }
+                th { colspan: 2, "Probability (%)" }
+                th { rowspan: 2, "Price" }
+                th { rowspan: 2, "Distinct materials" }
+                th { rowspan: 2, "Rare materials" }
+                th { rowspan: 2, "Material combination" }
+            }
+            tr {
+                th { "Gizmo" }
+                th { "Attempt" }
+            }
+            for line in front.lines() {
+                tr {
+                    td { "{line.level}" }
+                    td { perk_solver::result::format_float(line.prob_gizmo) }
+                    td { perk_solver::result::format_float(line.prob_attempt) }
+                    td { perk_solver::result::format_price(line.price) }
+                    td { "{perk_solver::result::distinct_material_count(&line.mat_combination)}" }
+                    td { "{perk_solver::result::rare_material_count(&line.mat_combination)}" }
+                    td { MatCombinationList(cx, &line.mat_combination) }
+                }
+            }
+        }
+    ))
+}
+
 pub fn ResultTable<'a>(cx: Scope<'a>, result: &Vec<Vec<ResultLine>>, args: &Args) -> Element<'a> {
     if let Some((best_gizmo_index, best_attempt_index, best_price_index)) =
         perk_solver::result::get_best_of_each(result)
     {
-        let best_wanted_index = match args.sort_type {
-            SortType::Gizmo => best_gizmo_index,
-            SortType::Attempt => best_attempt_index,
-            SortType::Price => best_price_index,
-        };
+        let best_wanted_index =
+            perk_solver::result::get_best_wanted_index(result, args.sort_type).unwrap();
 
         let best_wanted = &result[best_wanted_index][0];
         let best_gizmo_prob = result[best_gizmo_index][0].prob_gizmo;
         let best_attempt_prob = result[best_attempt_index][0].prob_attempt;
         let best_price = result[best_price_index][0].price;
+        let best_score = best_wanted.score;
+        let budget = args.sort_type == SortType::Budget;
 
         cx.render(rsx!(
             div {
@@ -68,18 +101,22 @@ pub fn ResultTable<'a>(cx: Scope<'a>, result: &Vec<Vec<ResultLine>>, args: &Args
                         th { rowspan: 2, "Level" }
                         th { colspan: 2, "Probability (%)" }
                         th { rowspan: 2, "Price" }
+                        if budget {
+                            rsx!(th { rowspan: 2, "Within budget (%)" })
+                        }
                     }
                     tr {
                         th { "Gizmo" }
                         th { "Attempt" }
                     }
-                    for (line, (r1, g1, _), (r2, g2, _), (r3, g3, _)) in result
+                    for (line, (r1, g1, _), (r2, g2, _), (r3, g3, _), (r4, g4, _)) in result
                         .iter()
                         .map(|x| (
                             x,
                             get_color(x[0].prob_gizmo / best_gizmo_prob),
                             get_color(x[0].prob_attempt / best_attempt_prob),
                             get_color(best_price / x[0].price),
+                            get_color(x[0].score / best_score),
                         ))
                     {
                         tr {
@@ -101,6 +138,12 @@ pub fn ResultTable<'a>(cx: Scope<'a>, result: &Vec<Vec<ResultLine>>, args: &Args
                                 background_color: "rgba({r3},{g3},0, 0.5)",
                                 perk_solver::result::format_price(line[0].price)
                             }
+                            if budget {
+                                rsx!(td {
+                                    background_color: "rgba({r4},{g4},0, 0.5)",
+                                    perk_solver::result::format_float(line[0].score)
+                                })
+                            }
                         }
                     }
                 }
@@ -112,7 +155,10 @@ pub fn ResultTable<'a>(cx: Scope<'a>, result: &Vec<Vec<ResultLine>>, args: &Args
                                 match args.sort_type {
                                     SortType::Gizmo => "Prob. per gizmo",
                                     SortType::Attempt => "Prob. per attempt",
-                                    SortType::Price => "Price"
+                                    SortType::Price => "Price",
+                                    SortType::Custom => "Objective",
+                                    SortType::Budget => "Prob. within budget",
+                                    SortType::Value => "Expected value",
                                 }
                             }
                             th { "Level" }
@@ -128,6 +174,7 @@ pub fn ResultTable<'a>(cx: Scope<'a>, result: &Vec<Vec<ResultLine>>, args: &Args
                                     SortType::Gizmo => perk_solver::result::format_float(best_wanted.prob_gizmo),
                                     SortType::Attempt => perk_solver::result::format_float(best_wanted.prob_attempt),
                                     SortType::Price => perk_solver::result::format_price(best_wanted.price),
+                                    SortType::Custom | SortType::Budget | SortType::Value => perk_solver::result::format_sort_value(best_wanted, args),
                                 }
                             }
                             td { "{best_wanted.level}" }
@@ -154,6 +201,7 @@ pub fn ResultTable<'a>(cx: Scope<'a>, result: &Vec<Vec<ResultLine>>, args: &Args
                                                 SortType::Gizmo => perk_solver::result::format_float(alt.prob_gizmo),
                                                 SortType::Attempt => perk_solver::result::format_float(alt.prob_attempt),
                                                 SortType::Price => perk_solver::result::format_price(alt.price),
+                                                SortType::Custom | SortType::Budget | SortType::Value => perk_solver::result::format_sort_value(alt, args),
                                             }
                                         }
                                         td { "{alt.level}" }
@@ -172,6 +220,13 @@ pub fn ResultTable<'a>(cx: Scope<'a>, result: &Vec<Vec<ResultLine>>, args: &Args
                             )
                         }
                     }
+                    div {
+                        b { "Recipe code: " }
+                        span {
+                            class: "recipe-code",
+                            RecipeCode::from_result(args, best_wanted).to_string()
+                        }
+                    }
                     p {
                         div {
                             "The materials are in the order in which they fill the gizmo when clicked upon."
@@ -0,0 +1,126 @@
+//! Output of `--format json`. The layout is documented in the readme, changes to it bump [`SCHEMA_VERSION`].
+use crate::prelude::*;
+use itertools::Itertools;
+use serde::Serialize;
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+pub struct GizmoSolveJson<'a> {
+    pub schema_version: u32,
+    pub args: &'a Args,
+    pub materials: &'a SplitMaterials,
+    /// Levels without any result are left out
+    pub levels: Vec<LevelJson<'a>>,
+    /// The result that is the best for the sort type
+    pub best: Option<&'a ResultLine>,
+    /// All gizmos the best result can make at its level, most likely first
+    pub distribution: Vec<Gizmo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pareto_front: Option<Vec<&'a ResultLine>>,
+}
+
+#[derive(Serialize)]
+pub struct LevelJson<'a> {
+    pub level: u8,
+    /// Best first, up to the alt count plus one
+    pub results: Vec<&'a ResultLine>,
+}
+
+#[derive(Serialize)]
+pub struct MaterialInputJson<'a> {
+    pub schema_version: u32,
+    pub gizmo_type: GizmoType,
+    pub ancient: bool,
+    pub level: u8,
+    pub materials: &'a [MaterialName],
+    pub distribution: &'a [Gizmo],
+}
+
+impl<'a> GizmoSolveJson<'a> {
+    pub fn new(
+        args: &'a Args,
+        materials: &'a SplitMaterials,
+        best_per_level: &'a [Vec<ResultLine>],
+        best: Option<&'a ResultLine>,
+        distribution: Vec<Gizmo>,
+    ) -> GizmoSolveJson<'a> {
+        let levels = best_per_level
+            .iter()
+            .map(|lines| lines.iter().filter(|x| x.prob_gizmo > 0.0).collect_vec())
+            .filter(|lines| !lines.is_empty())
+            .map(|results| LevelJson {
+                level: results[0].level,
+                results,
+            })
+            .collect();
+        GizmoSolveJson {
+            schema_version: SCHEMA_VERSION,
+            args,
+            materials,
+            levels,
+            best,
+            distribution,
+            pareto_front: None,
+        }
+    }
+}
+
+pub fn to_string<T: Serialize>(value: &T) -> String {
+    serde_json::to_string_pretty(value).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn gizmo_solve_layout() {
+        let args = Args {
+            invention_level: InventionLevel::Range(1, 137),
+            perk: PerkName::Precise,
+            rank: 5,
+            ..Default::default()
+        };
+        let materials = SplitMaterials {
+            conflict: vec![MaterialName::ArmadylComponents],
+            no_conflict: vec![],
+        };
+        let line = ResultLine {
+            level: 120,
+            prob_gizmo: 0.5,
+            prob_attempt: 0.25,
+            price: 1000.0,
+            mat_combination: Arc::new(vec![MaterialName::ArmadylComponents]),
+            ..Default::default()
+        };
+        let best_per_level = vec![vec![ResultLine::default()], vec![line.clone()]];
+        let mut gizmo = Gizmo::default();
+        gizmo.perks.0 = Perk {
+            name: PerkName::Precise,
+            rank: 5,
+        };
+        gizmo.probability = 0.5;
+
+        let json =
+            GizmoSolveJson::new(&args, &materials, &best_per_level, Some(&line), vec![gizmo]);
+        let value: serde_json::Value = serde_json::from_str(&to_string(&json)).unwrap();
+
+        assert_eq!(value["schema_version"], 1);
+        assert_eq!(
+            value["args"]["invention_level"],
+            serde_json::json!([1, 137])
+        );
+        assert_eq!(value["args"]["perk"], "Precise");
+        assert_eq!(value["args"]["sort_type"], "price");
+        assert_eq!(value["materials"]["conflict"][0], "Armadyl components");
+        assert_eq!(value["levels"].as_array().unwrap().len(), 1);
+        assert_eq!(value["levels"][0]["level"], 120);
+        assert_eq!(value["levels"][0]["results"][0]["prob_attempt"], 0.25);
+        assert_eq!(value["best"]["mat_combination"][0], "Armadyl components");
+        assert_eq!(value["distribution"][0]["perks"][0]["rank"], 5);
+        assert_eq!(value["distribution"][0]["perks"][1]["name"], "Empty");
+        assert!(value.get("pareto_front").is_none());
+    }
+}
@@ -102,22 +102,40 @@
 //! the comparison alternates with loop_index between `< 0` and `<= 0`, which we emulate by the bitwise AND
 //! (`loop_index % 2` has the same effect) to make the comparison `< 0` and `< 1`.
 
+pub mod blockers;
 pub mod component_prices;
+pub mod conflicts;
+pub mod cost_model;
 mod dice;
+pub mod dry_run;
+pub mod exclusion;
+pub mod explain;
 mod gizmo_cost_thresholds;
+pub mod gizmo_values;
 mod jagex_sort;
+pub mod json;
+pub mod neighbours;
+pub mod objective;
+pub mod order;
 mod perk_values;
 pub mod prelude;
+pub mod recipe_code;
 pub mod result;
+pub mod sensitivity;
 mod utils;
+pub mod wiki;
 
 use colored::Colorize;
-use component_prices::{load_component_prices, set_shell_price};
+use component_prices::{
+    disassembly::disassembly_report, load_layered_prices, shells::ShellRecipes, PriceMap,
+};
+use cost_model::{CostModel, DefaultCostModel};
 use gizmo_cost_thresholds::*;
 use indicatif::{ProgressBar, ProgressStyle};
 use itertools::Itertools;
 use perk_values::*;
 pub use prelude::*;
+use result::SolverOutput;
 use smallvec::{smallvec, SmallVec};
 use std::cmp::{self, Ord, PartialOrd};
 use std::sync::atomic::{self, Ordering::Relaxed};
@@ -138,13 +156,29 @@ pub struct SolverMetadata {
 pub struct Solver {
     wanted_gizmo: Gizmo,
     result_tx: Option<mpsc::SyncSender<Vec<ResultLine>>>,
-    result_handler: thread::JoinHandle<Vec<Vec<ResultLine>>>,
+    result_handler: thread::JoinHandle<SolverOutput>,
     data: Arc<Data>,
+    cost_model: Arc<dyn CostModel>,
     pub meta: SolverMetadata,
 }
 
 impl Solver {
-    pub fn new(args: Args, data: Data) -> Result<Solver, String> {
+    pub fn new(
+        args: Args,
+        data: Data,
+        prices: PriceMap,
+        shells: &ShellRecipes,
+    ) -> Result<Solver, String> {
+        let cost_model = DefaultCostModel::new(prices, shells.get(args.gizmo_type, args.ancient));
+        Solver::with_cost_model(args, data, Arc::new(cost_model))
+    }
+
+    /// Create a solver that estimates the price of the gizmos with a custom cost model instead of the default one
+    pub fn with_cost_model(
+        args: Args,
+        data: Data,
+        cost_model: Arc<dyn CostModel>,
+    ) -> Result<Solver, String> {
         let args = Arc::new(args);
         let data = Arc::new(data);
         let wanted_gizmo = Gizmo {
@@ -162,8 +196,6 @@ impl Solver {
         };
         validate_input(&args, wanted_gizmo, &data)?;
         let materials = get_materials(&args, &data, wanted_gizmo)?;
-        load_component_prices(&args.price_file, false)?;
-        set_shell_price(args.gizmo_type, args.ancient);
         let materials = Arc::new(split_materials(&args, &data, wanted_gizmo, materials));
         let total_combination_count = calc_combination_count(
             materials.conflict.len(),
@@ -180,6 +212,7 @@ impl Solver {
             result_tx: Some(result_tx),
             result_handler,
             data,
+            cost_model,
             meta: SolverMetadata {
                 materials,
                 bar_progress,
@@ -190,7 +223,87 @@ impl Solver {
         })
     }
 
-    pub fn run(mut self) -> Vec<Vec<ResultLine>> {
+    /// Probabilities and price of a recipe and of every recipe that differs by one material, using the materials that
+    /// can make the wanted gizmo for replacing and adding
+    pub fn neighbours(&self, recipe: &neighbours::Recipe) -> neighbours::NeighbourReport {
+        let args = self.meta.args.as_ref();
+        let budgets = vec![Budget::create(recipe.level as usize, args.ancient)];
+        let evaluate = |mats: Vec<MaterialName>| {
+            let mut line = calc_wanted_gizmo_probabilities(
+                &self.data,
+                args,
+                &budgets,
+                mats.clone(),
+                self.wanted_gizmo,
+                self.cost_model.as_ref(),
+                &mut None,
+            )
+            .pop()
+            .unwrap_or(ResultLine {
+                level: recipe.level,
+                price: f64::INFINITY,
+                mat_combination: Arc::new(mats),
+                ..Default::default()
+            });
+            result::score_line(args, &mut line);
+            line
+        };
+
+        let candidates = self
+            .meta
+            .materials
+            .conflict
+            .iter()
+            .chain(&self.meta.materials.no_conflict)
+            .copied()
+            .sorted()
+            .collect_vec();
+        let slot_count = if args.ancient { 9 } else { 5 };
+        let neighbours =
+            neighbours::one_edit_neighbours(&recipe.materials, &candidates, slot_count)
+                .into_iter()
+                .map(|(edit, mats)| neighbours::Neighbour {
+                    edit,
+                    line: evaluate(mats),
+                })
+                .collect();
+        neighbours::NeighbourReport::new(
+            evaluate(recipe.materials.clone()),
+            neighbours,
+            args.sort_type,
+        )
+    }
+
+    /// Amount of combinations and the runtime estimated from timing a sample of them, without solving
+    pub fn dry_run(&self) -> dry_run::DryRun {
+        dry_run::dry_run(
+            &self.data,
+            &self.meta.args,
+            self.wanted_gizmo,
+            &self.meta.materials,
+            self.cost_model.as_ref(),
+        )
+    }
+
+    /// Collisions of the conflict materials and their effect on the amount of combinations. The suggested exclusions
+    /// are added with [`conflicts::ConflictReport::suggest`] after solving.
+    pub fn conflict_report(&self) -> conflicts::ConflictReport {
+        conflicts::ConflictReport::new(
+            &self.data,
+            &self.meta.args,
+            self.wanted_gizmo,
+            &self.meta.materials,
+        )
+    }
+
+    pub fn run(self) -> Vec<Vec<ResultLine>> {
+        self.run_detailed().best_per_level
+    }
+
+    /// Same as [`Solver::run`] but also returns the pool of all results when [`Args::keep_pool`] is set, which can be
+    /// ranked again with [`result::ResultPool::rerank`] after the prices change, and the Pareto front when
+    /// [`Args::pareto`] is set
+    pub fn run_detailed(mut self) -> SolverOutput {
         let budgets = Arc::new(generate_budgets(
             &self.meta.args.invention_level,
             self.meta.args.ancient,
@@ -214,6 +327,7 @@ impl Solver {
                 let bar_progress = self.meta.bar_progress.clone();
                 let materials = self.meta.materials.clone();
                 let cancel_signal = self.meta.cancel_signal.clone();
+                let cost_model = self.cost_model.clone();
                 pool.execute(move || {
                     // Order does no matter when none of the materials used have a cost conflict with the wanted perks
                     for mat_combination in materials
@@ -231,6 +345,7 @@ impl Solver {
                             &budgets,
                             mat_combination,
                             wanted_gizmo,
+                            cost_model.as_ref(),
                             &mut None,
                         );
                         bar_progress.fetch_add(1, Relaxed);
@@ -272,6 +387,7 @@ impl Solver {
                                 let bar_progress = self.meta.bar_progress.clone();
                                 let mats = mats.clone();
                                 let cancel_signal = self.meta.cancel_signal.clone();
+                                let cost_model = self.cost_model.clone();
                                 while pool.queued_count() > 100000 {
                                     std::thread::sleep(ten_millis);
                                 }
@@ -279,33 +395,22 @@ impl Solver {
                                     break 'cancel;
                                 }
                                 pool.execute(move || {
-                                    let mut has_conflict = None;
-                                    for ordered_mats in mats
-                                        .iter()
-                                        .copied()
-                                        .permutations(n_conflict_mats + n_noconflict_mats)
-                                    {
-                                        if cancel_signal.load(Relaxed) {
-                                            break;
-                                        }
-                                        if has_conflict.is_none() || (has_conflict.unwrap() == true)
-                                        {
-                                            let mut mat_combination = ordered_mats;
-                                            mat_combination.extend_from_slice(&unordered_mats);
-                                            let lines = calc_wanted_gizmo_probabilities(
-                                                &data,
-                                                &args,
-                                                &budgets,
-                                                mat_combination,
-                                                wanted_gizmo,
-                                                &mut has_conflict,
-                                            );
-                                            if lines.len() > 0 {
+                                    check_orders(
+                                        &data,
+                                        &args,
+                                        &budgets,
+                                        &mats,
+                                        &unordered_mats,
+                                        wanted_gizmo,
+                                        cost_model.as_ref(),
+                                        |lines| {
+                                            if !lines.is_empty() {
                                                 tx.send(lines).ok();
                                             }
-                                        }
-                                        bar_progress.fetch_add(1, Relaxed);
-                                    }
+                                            bar_progress.fetch_add(1, Relaxed);
+                                            !cancel_signal.load(Relaxed)
+                                        },
+                                    );
                                 });
                             }
                         }
@@ -323,10 +428,36 @@ impl Solver {
 
 pub fn perk_solver(args: Args) {
     let data = Data::load();
-    let solver = Solver::new(args, data).unwrap_or_else(|err| utils::print_error(err.as_str()));
+    let loaded = load_layered_prices(&args, false)
+        .and_then(|loaded| {
+            loaded.check(args.strict_prices)?;
+            Ok(loaded)
+        })
+        .unwrap_or_else(|err| utils::print_error(err.as_str()));
+    let text = args.format == OutputFormat::Text;
+    if !loaded.disassembly.is_empty() && text {
+        println!("{}", disassembly_report(&loaded.disassembly));
+    }
+    let shell = loaded.shells.get(args.gizmo_type, args.ancient).clone();
+    let solver = Solver::new(args, data, loaded.prices.clone(), &loaded.shells)
+        .unwrap_or_else(|err| utils::print_error(err.as_str()));
     let meta = solver.meta.clone();
-    println!("{}\n", meta.args.as_ref());
-    println!("{}\n", meta.materials);
+    if text {
+        println!("{}\n", meta.args.as_ref());
+        println!("{}\n", meta.materials);
+    }
+
+    if let Some(recipe) = &meta.args.neighbours {
+        print!("{}", solver.neighbours(recipe));
+        return;
+    }
+
+    if meta.args.dry_run {
+        print!("{}", solver.dry_run());
+        return;
+    }
+
+    let conflict_report = meta.args.conflicts.then(|| solver.conflict_report());
 
     // let x = setupData.bar_progress.clone();
     let bar_handler = thread::spawn(move || {
@@ -349,13 +480,91 @@ pub fn perk_solver(args: Args) {
         }
     });
 
-    let best_per_level = solver.run();
+    let data = Arc::clone(&solver.data);
+    let SolverOutput {
+        best_per_level,
+        pareto_front,
+        pool,
+    } = solver.run_detailed();
 
     bar_handler.join().ok();
-    println!("\n");
+    result::write_best_mats_to_file(&best_per_level, &meta.args);
 
+    if meta.args.format != OutputFormat::Text && meta.args.sensitivity.is_some() {
+        utils::print_warning("The sensitivity analysis is only shown with --format text");
+    }
+    if meta.args.format != OutputFormat::Text && meta.args.exclusion_sweep {
+        utils::print_warning("The exclusion sweep is only shown with --format text");
+    }
+    if meta.args.format != OutputFormat::Text && meta.args.conflicts {
+        utils::print_warning("The conflict materials report is only shown with --format text");
+    }
+    match meta.args.format {
+        OutputFormat::Text => (),
+        OutputFormat::Json => {
+            let best = result::get_best_wanted_index(&best_per_level, meta.args.sort_type)
+                .map(|i| &best_per_level[i][0]);
+            let distribution = best.map_or(vec![], |best| {
+                calc_gizmo_probabilities(
+                    &data,
+                    &Budget::create(best.level as usize, meta.args.ancient),
+                    &best.mat_combination,
+                    meta.args.gizmo_type,
+                    meta.args.ancient,
+                )
+            });
+            let mut json = json::GizmoSolveJson::new(
+                &meta.args,
+                &meta.materials,
+                &best_per_level,
+                best,
+                distribution,
+            );
+            json.pareto_front = pareto_front.as_ref().map(|x| x.lines());
+            println!("{}", json::to_string(&json));
+            return;
+        }
+        OutputFormat::Wiki => {
+            println!("{}", wiki::result_markup(&best_per_level, &meta.args));
+            return;
+        }
+    }
+
+    println!("\n");
     result::print_result(&best_per_level, &meta.args);
-    result::write_best_mats_to_file(&best_per_level, &meta.args);
+
+    if let Some(front) = pareto_front {
+        println!();
+        result::print_pareto_front(&front);
+    }
+
+    if let Some(perturbation) = meta.args.sensitivity {
+        match sensitivity::analyse(
+            &best_per_level,
+            &loaded.prices,
+            &shell,
+            perturbation,
+            meta.args.monte_carlo_samples,
+        ) {
+            Some(report) => println!("\n{report}"),
+            None => utils::print_warning(
+                "Sensitivity analysis needs at least two results, use --alt-count to show more",
+            ),
+        }
+    }
+
+    if let Some(pool) = &pool {
+        if let Some(sweep) = exclusion::sweep(&best_per_level, pool, meta.args.sort_type) {
+            println!("\n{sweep}");
+        }
+    }
+
+    if let Some(mut report) = conflict_report {
+        if let Some(pool) = &pool {
+            report.suggest(&best_per_level, pool, meta.args.sort_type);
+        }
+        println!("\n{report}");
+    }
 }
 
 /// Returns a vector of all possible gizmos and their probabilities
@@ -471,12 +680,50 @@ fn validate_input(args: &Args, wanted_gizmo: Gizmo, data: &Data) -> Result<(), S
     Ok(())
 }
 
+/// Check every order of the distinct materials, each followed by the unordered ones. Once the materials turn out to have
+/// no cost conflict with the wanted perks the order doesn't matter anymore, so the other orders are skipped. Calls
+/// `on_order` with the lines of each order, empty for skipped orders, and stops when it returns false.
+#[allow(clippy::too_many_arguments)]
+fn check_orders(
+    data: &Data,
+    args: &Args,
+    budgets: &Vec<Budget>,
+    mats: &[MaterialName],
+    unordered_mats: &[MaterialName],
+    wanted_gizmo: Gizmo,
+    cost_model: &dyn CostModel,
+    mut on_order: impl FnMut(Vec<ResultLine>) -> bool,
+) {
+    let mut has_conflict = None;
+    for ordered_mats in mats.iter().copied().permutations(mats.len()) {
+        let lines = if has_conflict != Some(false) {
+            let mut mat_combination = ordered_mats;
+            mat_combination.extend_from_slice(unordered_mats);
+            calc_wanted_gizmo_probabilities(
+                data,
+                args,
+                budgets,
+                mat_combination,
+                wanted_gizmo,
+                cost_model,
+                &mut has_conflict,
+            )
+        } else {
+            vec![]
+        };
+        if !on_order(lines) {
+            break;
+        }
+    }
+}
+
 fn calc_wanted_gizmo_probabilities(
     data: &Data,
     args: &Args,
     budgets: &Vec<Budget>,
     input_materials: Vec<MaterialName>,
     wanted_gizmo: Gizmo,
+    cost_model: &dyn CostModel,
     has_conflict: &mut Option<bool>,
 ) -> Vec<ResultLine> {
     let perk_values = get_perk_values(data, &input_materials, args.gizmo_type, args.ancient);
@@ -490,17 +737,44 @@ fn calc_wanted_gizmo_probabilities(
     if has_conflict.is_none() {
         has_conflict.replace(contains_conflict_ranks(data, &perk_values, wanted_gizmo));
     }
-    let mut permutations = permutate_perk_ranks(&perk_values, Some(wanted_gizmo));
+    let p_empty: SmallVec<[f64; 96]> = budgets
+        .iter()
+        .map(|x| get_empty_gizmo_chance(x, &perk_values))
+        .collect();
+    let (p_wanted, values) = if args.gizmo_values.is_empty() {
+        (
+            calc_wanted_probabilities(args, budgets, &perk_values, wanted_gizmo),
+            smallvec![0.0; budgets.len()],
+        )
+    } else {
+        calc_expected_values(args, budgets, &perk_values, wanted_gizmo)
+    };
+
+    let input_materials = Arc::new(input_materials);
+    itertools::multizip((budgets, p_wanted, p_empty, values))
+        .filter(|(_, pw, _, _)| *pw > 0.0)
+        .map(|(budget, pw, pe, value)| ResultLine {
+            value,
+            ..ResultLine::create(budget.level, pw, pe, input_materials.clone(), cost_model)
+        })
+        .collect()
+}
+
+/// Probability per attempt of the wanted gizmo for each budget. Only the rank combinations with the wanted ranks are
+/// needed for this.
+fn calc_wanted_probabilities(
+    args: &Args,
+    budgets: &[Budget],
+    perk_values: &PerkValuesVec,
+    wanted_gizmo: Gizmo,
+) -> SmallVec<[f64; 96]> {
+    let mut permutations = permutate_perk_ranks(perk_values, Some(wanted_gizmo));
 
     for x in permutations.iter_mut() {
         jagex_sort::jagex_quicksort(x);
     }
 
     let mut p_wanted: SmallVec<[f64; 96]> = smallvec![0.0; budgets.len()];
-    let p_empty: SmallVec<[f64; 96]> = budgets
-        .iter()
-        .map(|x| get_empty_gizmo_chance(x, &perk_values))
-        .collect();
     for combination in permutations.iter() {
         let mut cost_thresholds = if args.fuzzy {
             fuzzy_find_wanted_gizmo_cost_thresholds(
@@ -527,12 +801,48 @@ fn calc_wanted_gizmo_probabilities(
             }
         }
     }
+    p_wanted
+}
 
-    let input_materials = Arc::new(input_materials);
-    itertools::multizip((budgets, p_wanted, p_empty))
-        .filter(|(_, pw, _)| *pw > 0.0)
-        .map(|(budget, pw, pe)| ResultLine::create(budget.level, pw, pe, input_materials.clone()))
-        .collect()
+/// Probability per attempt of the wanted gizmo and expected value per attempt of all gizmos that can be made, for each
+/// budget. The value needs every rank combination, so the probability of the wanted gizmo is taken from the same pass.
+fn calc_expected_values(
+    args: &Args,
+    budgets: &[Budget],
+    perk_values: &PerkValuesVec,
+    wanted_gizmo: Gizmo,
+) -> (SmallVec<[f64; 96]>, SmallVec<[f64; 96]>) {
+    let mut p_wanted: SmallVec<[f64; 96]> = smallvec![0.0; budgets.len()];
+    let mut values: SmallVec<[f64; 96]> = smallvec![0.0; budgets.len()];
+    let max_range = budgets.last().unwrap().range.max;
+    for mut combination in permutate_perk_ranks(perk_values, None) {
+        jagex_sort::jagex_quicksort(&mut combination);
+        let mut cost_thresholds = find_gizmo_cost_thresholds(&combination, max_range);
+        // Neither depends on the budget
+        let is_wanted = cost_thresholds
+            .iter()
+            .map(|x| {
+                (args.fuzzy && x.contains(&wanted_gizmo)) || (!args.fuzzy && x.same(&wanted_gizmo))
+            })
+            .collect::<SmallVec<[bool; 16]>>();
+        let gizmo_value = cost_thresholds
+            .iter()
+            .map(|x| args.gizmo_values.value(x))
+            .collect::<SmallVec<[f64; 16]>>();
+        if !is_wanted.contains(&true) && gizmo_value.iter().all(|x| *x == 0.0) {
+            continue;
+        }
+        for ((budget, pw), value) in budgets.iter().zip(&mut p_wanted).zip(&mut values) {
+            calc_probability_from_thresholds(&mut cost_thresholds, budget, combination.probability);
+            for ((x, wanted), v) in cost_thresholds.iter().zip(&is_wanted).zip(&gizmo_value) {
+                if *wanted {
+                    *pw += x.probability;
+                }
+                *value += x.probability * v;
+            }
+        }
+    }
+    (p_wanted, values)
 }
 
 fn calc_probability_from_thresholds(cth_in: &mut [Gizmo], budget: &Budget, comb_probability: f64) {
@@ -661,25 +971,10 @@ fn split_materials(
     }
 
     for mat in mats {
-        let mut is_conflict = false;
-        'comp: for comp_values in data.comps[mat][args.gizmo_type].iter() {
-            if comp_values.perk != wanted_gizmo.perks.0.name
-                && comp_values.perk != wanted_gizmo.perks.1.name
-            {
-                for perk_rank in data.perks[comp_values.perk].ranks.iter() {
-                    if perk_rank.rank > 0
-                        && (perk_rank.cost == cost_p1 || perk_rank.cost == cost_p2)
-                    {
-                        conflict.push(mat);
-                        is_conflict = true;
-                        break 'comp;
-                    }
-                }
-            }
-        }
-
-        if !is_conflict {
+        if conflicts::find_collisions(data, args.gizmo_type, wanted_gizmo, mat).is_empty() {
             no_conflict.push(mat);
+        } else {
+            conflict.push(mat);
         }
     }
 
@@ -712,11 +1007,23 @@ fn generate_budgets(invention_level: &InventionLevel, ancient: bool) -> Vec<Budg
 /// so we have to check every order. But it's only the order of first occurrence that matters so the pattern abbc is the
 /// same as abcb. The order of the repeated materials also doesn't matter so abcbc is the same as abccb.
 fn calc_combination_count(conflict_size: usize, no_conflict_size: usize, is_ancient: bool) -> u64 {
+    let count: f64 = calc_combination_counts_per_slot(conflict_size, no_conflict_size, is_ancient)
+        .iter()
+        .sum();
+    (count + 0.5) as u64
+}
+
+/// Same as [`calc_combination_count`] for each amount of used slots, starting at 1
+fn calc_combination_counts_per_slot(
+    conflict_size: usize,
+    no_conflict_size: usize,
+    is_ancient: bool,
+) -> Vec<f64> {
     let slot_count = if is_ancient { 9 } else { 5 };
-    let mut count = 0.0;
+    let mut counts = vec![];
 
     for i in 1..=slot_count {
-        count += dice::choose(no_conflict_size + i - 1, i); // Combination with repetition
+        let mut count = dice::choose(no_conflict_size + i - 1, i); // Combination with repetition
 
         for j in 1..=cmp::min(i, conflict_size) {
             let mut x = 0.0;
@@ -728,15 +1035,17 @@ fn calc_combination_count(conflict_size: usize, no_conflict_size: usize, is_anci
 
             count += x * dice::choose(conflict_size, j);
         }
+        counts.push(count);
     }
 
-    (count + 0.5) as u64
+    counts
 }
 
 #[rustfmt::skip]
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cost_model::FreeCostModel;
     use crate::utils::{check_index, check_index_relative, check_len};
     use once_cell::sync::Lazy;
 
@@ -1157,6 +1466,7 @@ mod tests {
                 &budgets,
                 input_materials,
                 wanted_gizmo,
+                &FreeCostModel,
                 &mut None,
             );
             assert_resultlines_eq(&actual, &expected);
@@ -1197,6 +1507,7 @@ mod tests {
                 &budgets,
                 input_materials,
                 wanted_gizmo,
+                &FreeCostModel,
                 &mut None,
             );
             assert_resultlines_eq(&actual, &expected);
@@ -1235,6 +1546,7 @@ mod tests {
                 &budgets,
                 input_materials,
                 wanted_gizmo,
+                &FreeCostModel,
                 &mut None,
             );
             assert_resultlines_eq(&actual, &expected);
@@ -1275,9 +1587,167 @@ mod tests {
                 &budgets,
                 input_materials,
                 wanted_gizmo,
+                &FreeCostModel,
                 &mut None,
             );
             assert_resultlines_eq(&actual, &expected);
         }
     }
+
+    mod solver_tests {
+        use super::*;
+        use strum::IntoEnumIterator;
+
+        fn uniform_prices(price: f64) -> PriceMap {
+            let mut prices = PriceMap::new();
+            for mat in MaterialName::iter() {
+                prices.set(mat, price);
+            }
+            prices
+        }
+
+        #[test]
+        fn concurrent_solvers_use_their_own_prices() {
+            let args = Args {
+                invention_level: InventionLevel::Single(120),
+                gizmo_type: GizmoType::Weapon,
+                perk: PerkName::Precise,
+                rank: 1,
+                out_file: None,
+                price_file: None,
+                ..Default::default()
+            };
+            let cheap = Solver::new(args.clone(), Data::load(), uniform_prices(1.0), &ShellRecipes::default()).unwrap();
+            let expensive = Solver::new(args, Data::load(), uniform_prices(2.0), &ShellRecipes::default()).unwrap();
+
+            let cheap = thread::spawn(move || cheap.run());
+            let expensive = thread::spawn(move || expensive.run());
+            let cheap = cheap.join().unwrap();
+            let expensive = expensive.join().unwrap();
+
+            assert_eq!(cheap[0][0].mat_combination, expensive[0][0].mat_combination);
+            approx::assert_relative_eq!(cheap[0][0].price * 2.0, expensive[0][0].price);
+        }
+
+        #[test]
+        fn rerank_matches_a_new_solve() {
+            let args = Args {
+                invention_level: InventionLevel::Range(100, 104),
+                gizmo_type: GizmoType::Weapon,
+                perk: PerkName::Precise,
+                rank: 1,
+                out_file: None,
+                price_file: None,
+                result_depth: 3,
+                keep_pool: true,
+                ..Default::default()
+            };
+            let shells = ShellRecipes::default();
+            let mut expensive_armadyl = uniform_prices(1.0);
+            expensive_armadyl.set(MaterialName::ArmadylComponents, 1e6);
+            let new_prices = uniform_prices(1.0);
+
+            let output = Solver::new(args.clone(), Data::load(), expensive_armadyl, &shells).unwrap().run_detailed();
+            let pool = output.pool.unwrap();
+            let reranked = pool.rerank(&DefaultCostModel::new(new_prices.clone(), shells.get(GizmoType::Weapon, false)));
+            let solved = Solver::new(args, Data::load(), new_prices, &shells).unwrap().run();
+
+            assert!(!pool.is_empty());
+            assert_eq!(reranked.len(), solved.len());
+            for (x, y) in reranked.iter().flatten().zip(solved.iter().flatten()) {
+                assert_eq!(x.level, y.level);
+                approx::assert_relative_eq!(x.price, y.price);
+                assert_eq!(
+                    x.mat_combination.iter().sorted().collect_vec(),
+                    y.mat_combination.iter().sorted().collect_vec()
+                );
+            }
+        }
+
+        #[test]
+        fn exclusion_sweep_matches_a_solve_without_the_material() {
+            let args = Args {
+                invention_level: InventionLevel::Single(120),
+                gizmo_type: GizmoType::Weapon,
+                perk: PerkName::Precise,
+                rank: 2,
+                out_file: None,
+                price_file: None,
+                keep_pool: true,
+                ..Default::default()
+            };
+            let shells = ShellRecipes::default();
+            let output = Solver::new(args.clone(), Data::load(), uniform_prices(1.0), &shells).unwrap().run_detailed();
+            let sweep = exclusion::sweep(&output.best_per_level, output.pool.as_ref().unwrap(), SortType::Price).unwrap();
+
+            assert!(!sweep.fallbacks.is_empty());
+            for fallback in sweep.fallbacks.iter() {
+                let args = Args {
+                    exclude: vec![fallback.excluded],
+                    keep_pool: false,
+                    ..args.clone()
+                };
+                let solved = Solver::new(args, Data::load(), uniform_prices(1.0), &shells).unwrap().run();
+                let line = fallback.line.as_ref().unwrap();
+                assert!(!line.mat_combination.contains(&fallback.excluded));
+                approx::assert_relative_eq!(line.price, solved[0][0].price);
+                assert!(fallback.penalty(&sweep.best) >= 0.0);
+            }
+        }
+
+        #[test]
+        fn expected_value_of_only_the_wanted_gizmo() {
+            let gizmo_values = gizmo_values::load_gizmo_values(&None, &[String::from("precise 1 + empty=1000")]).unwrap();
+            let args = Args {
+                invention_level: InventionLevel::Single(120),
+                gizmo_type: GizmoType::Weapon,
+                perk: PerkName::Precise,
+                rank: 1,
+                sort_type: SortType::Value,
+                gizmo_values,
+                out_file: None,
+                price_file: None,
+                result_depth: 3,
+                ..Default::default()
+            };
+            let result = Solver::new(args, Data::load(), uniform_prices(1.0), &ShellRecipes::default()).unwrap().run();
+
+            for line in result.iter().flatten().filter(|x| x.prob_attempt > 0.0) {
+                approx::assert_relative_eq!(line.value, line.prob_attempt * 1000.0, max_relative = 1e-9);
+                approx::assert_relative_eq!(line.score, line.value - line.price * line.prob_attempt, max_relative = 1e-9);
+            }
+        }
+
+        #[test]
+        fn expected_values_keep_the_wanted_probability() {
+            use MaterialName::*;
+            let gizmo_values = gizmo_values::load_gizmo_values(&None, &[String::from("mobile 1=1000")]).unwrap();
+            let mobile = Gizmo {
+                perks: (Perk { name: PerkName::Mobile, rank: 1 }, Perk::default()),
+                ..Default::default()
+            };
+            let budgets = generate_budgets(&InventionLevel::Range(90, 99), false);
+            for fuzzy in [false, true] {
+                let args = Args { gizmo_type: GizmoType::Armour, fuzzy, ..Default::default() };
+                let valued = Args { sort_type: SortType::Value, gizmo_values: gizmo_values.clone(), ..args.clone() };
+                for mats in [
+                    vec![DextrousComponents, ConnectorParts, FlexibleParts],
+                    vec![ConnectorParts, DextrousComponents, ConnectorParts, FlexibleParts, FlexibleParts],
+                ] {
+                    let lines = calc_wanted_gizmo_probabilities(&DATA, &args, &budgets, mats.clone(), mobile, &FreeCostModel, &mut None);
+                    let valued_lines = calc_wanted_gizmo_probabilities(&DATA, &valued, &budgets, mats, mobile, &FreeCostModel, &mut None);
+
+                    assert!(!lines.is_empty());
+                    check_len(&valued_lines, &lines);
+                    for (x, y) in valued_lines.iter().zip(lines.iter()) {
+                        approx::assert_relative_eq!(x.prob_attempt, y.prob_attempt, max_relative = 1e-12);
+                        if !fuzzy {
+                            // Only mobile 1 without second perk is wanted, with a second perk it's worth 1000 as well
+                            assert!(x.value >= x.prob_attempt * 1000.0 * (1.0 - 1e-12));
+                        }
+                    }
+                }
+            }
+        }
+    }
 }
@@ -1,6 +1,10 @@
 mod utils;
 use clap::Parser;
-use perk_solver::{calc_gizmo_probabilities, perk_solver, prelude::*};
+use itertools::Itertools;
+use perk_solver::{
+    blockers, calc_gizmo_probabilities, explain, gizmo_values::parse_gizmo, json, order,
+    perk_solver, prelude::*, recipe_code::RecipeCode, wiki,
+};
 use std::str::FromStr;
 
 fn main() {
@@ -14,26 +18,47 @@ fn main() {
             let args = Args::create(&cli).unwrap_or_else(|err| utils::print_error(err.as_str()));
             perk_solver(args);
         }
-        Commands::MaterialInput { mats } => {
+        Commands::MaterialInput { ref recipe } => {
             let data = Data::load();
-            let mut materials = vec![];
-            for mat_str in mats {
-                let mat = MaterialName::from_str(&mat_str);
-                match mat {
-                    Ok(mat) => materials.push(mat),
-                    Err(err) => utils::print_error(format!("{err} '{mat_str}'").as_str()),
-                }
-            }
-            if (!cli.ancient && materials.len() > 5) || materials.len() > 9 {
-                utils::print_error("Too many materials")
+            let wiki::CalcLink {
+                gizmo_type,
+                level,
+                ancient,
+                materials,
+            } = read_recipe(&cli, recipe);
+            let imported = recipe.link.is_some() || recipe.code.is_some();
+            let budget = Budget::create(level as usize, ancient);
+            let gizmos = calc_gizmo_probabilities(&data, &budget, &materials, gizmo_type, ancient);
+
+            if cli.format == OutputFormat::Wiki {
+                println!(
+                    "{}",
+                    wiki::distribution_markup(&materials, &gizmos, gizmo_type, level)
+                );
+                return;
             }
-            if cli.invention_level[0] == 0 || cli.invention_level[0] > 137 {
-                utils::print_error("Invalid invention level")
+            if cli.format == OutputFormat::Json {
+                let output = json::MaterialInputJson {
+                    schema_version: json::SCHEMA_VERSION,
+                    gizmo_type,
+                    ancient,
+                    level,
+                    materials: &materials,
+                    distribution: &gizmos,
+                };
+                println!("{}", json::to_string(&output));
+                return;
             }
-            let budget = Budget::create(cli.invention_level[0] as usize, cli.ancient);
-            let gizmos =
-                calc_gizmo_probabilities(&data, &budget, &materials, cli.gizmo_type, cli.ancient);
 
+            if imported {
+                println!(
+                    "{} {} gizmo, level {}: {}\n",
+                    if ancient { "Ancient" } else { "Normal" },
+                    gizmo_type.to_string().to_lowercase(),
+                    level,
+                    materials.iter().join(", ")
+                );
+            }
             for gizmo in gizmos {
                 let prob_str = format!("{}", gizmo.probability);
                 let zeros = prob_str
@@ -49,12 +74,155 @@ fn main() {
                 );
             }
         }
+        Commands::Explain { ref recipe } => {
+            let recipe = read_recipe(&cli, recipe);
+            if cli.format != OutputFormat::Text {
+                utils::print_warning("The explain command only has text output");
+            }
+            let explanation = explain::explain(
+                &Data::load(),
+                Budget::create(recipe.level as usize, recipe.ancient),
+                &recipe.materials,
+                recipe.gizmo_type,
+                recipe.ancient,
+            );
+            print!("{explanation}");
+        }
+        Commands::Blockers {
+            ref gizmo,
+            ref recipe,
+        } => {
+            let (wanted, any_second) = read_wanted_gizmo(gizmo, recipe);
+            let recipe = read_recipe(&cli, recipe);
+            if cli.format != OutputFormat::Text {
+                utils::print_warning("The blockers command only has text output");
+            }
+            let report = blockers::find_blockers(
+                &Data::load(),
+                &Budget::create(recipe.level as usize, recipe.ancient),
+                &recipe.materials,
+                recipe.gizmo_type,
+                recipe.ancient,
+                wanted,
+                any_second,
+            );
+            print!("{report}");
+        }
+        Commands::Order {
+            ref gizmo,
+            ref recipe,
+        } => {
+            let (wanted, any_second) = read_wanted_gizmo(gizmo, recipe);
+            let recipe = read_recipe(&cli, recipe);
+            if cli.format != OutputFormat::Text {
+                utils::print_warning("The order command only has text output");
+            }
+            let report = order::optimise_order(
+                &Data::load(),
+                &Budget::create(recipe.level as usize, recipe.ancient),
+                &recipe.materials,
+                recipe.gizmo_type,
+                recipe.ancient,
+                wanted,
+                any_second,
+            );
+            print!("{report}");
+        }
     }
 
     #[cfg(feature = "precise-time")]
     println!("\n{:?}", timer.elapsed());
 }
 
+/// Materials, gizmo type, level and ancient from the command line, a calculator link or a recipe code
+fn read_recipe(cli: &Cli, recipe: &RecipeArgs) -> wiki::CalcLink {
+    let link = match (&recipe.link, &recipe.code) {
+        (Some(url), _) => {
+            Some(wiki::parse_wiki_calc_link(url).unwrap_or_else(|err| utils::print_error(&err)))
+        }
+        (_, Some(code)) => Some(calc_link_of_code(code)),
+        _ => None,
+    };
+    let mut materials = vec![];
+    for mat_str in recipe.mats.iter() {
+        let mat = MaterialName::from_str(mat_str);
+        match mat {
+            Ok(mat) => materials.push(mat),
+            Err(err) => utils::print_error(format!("{err} '{mat_str}'").as_str()),
+        }
+    }
+    if let Some(link) = &link {
+        materials = link.materials.clone();
+    }
+    let ancient = cli
+        .ancient
+        .unwrap_or_else(|| link.as_ref().is_some_and(|x| x.ancient));
+    let gizmo_type = cli
+        .gizmo_type
+        .or(link.as_ref().map(|x| x.gizmo_type))
+        .unwrap_or_else(|| utils::print_error("Missing gizmo type"));
+    let level = cli
+        .invention_level
+        .first()
+        .copied()
+        .or(link.as_ref().map(|x| x.level))
+        .unwrap_or_else(|| utils::print_error("Missing invention level"));
+
+    if (!ancient && materials.len() > 5) || materials.len() > 9 {
+        utils::print_error("Too many materials")
+    }
+    if level == 0 || level > 137 {
+        utils::print_error("Invalid invention level")
+    }
+    wiki::CalcLink {
+        gizmo_type,
+        level,
+        ancient,
+        materials,
+    }
+}
+
+/// Wanted gizmo from the `--gizmo` option, or else from the perks of the recipe code
+fn read_wanted_gizmo(gizmo: &Option<String>, recipe: &RecipeArgs) -> (Gizmo, bool) {
+    match gizmo {
+        Some(gizmo) => parse_gizmo(gizmo),
+        None => {
+            let code = RecipeCode::parse(recipe.code.as_ref().unwrap())
+                .unwrap_or_else(|err| utils::print_error(&err));
+            if code.perks.0.is_empty() {
+                utils::print_error("The recipe code has no perks, use --gizmo")
+            }
+            Ok((
+                Gizmo {
+                    perks: code.perks,
+                    ..Default::default()
+                },
+                code.fuzzy,
+            ))
+        }
+    }
+    .unwrap_or_else(|err| utils::print_error(&err))
+}
+
+fn calc_link_of_code(code: &str) -> wiki::CalcLink {
+    let code = RecipeCode::parse(code).unwrap_or_else(|err| utils::print_error(&err));
+    let level = match code.level {
+        InventionLevel::Single(x) => x,
+        InventionLevel::Range(..) => {
+            utils::print_error("The recipe code has a level range instead of a single level")
+        }
+    };
+    if code.materials.is_empty() {
+        utils::print_error("The recipe code has no materials")
+    }
+    wiki::CalcLink {
+        gizmo_type: code.gizmo_type,
+        level,
+        ancient: code.ancient,
+        materials: code.materials,
+    }
+}
+
 fn perk_to_string(data: &Data, perk: PerkName, rank: u8) -> String {
     if data.perks[perk].ranks.len() <= 2 {
         perk.to_string()
@@ -0,0 +1,307 @@
+//! Effect of replacing, adding, removing or swapping one material of a recipe
+use crate::{
+    prelude::*,
+    recipe_code::RecipeCode,
+    result::{format_float, format_price, format_signed_price},
+};
+use colored::Colorize;
+use itertools::Itertools;
+use serde::Serialize;
+use std::{fmt, str::FromStr};
+
+/// Materials in the order they fill the gizmo, at a single level
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Recipe {
+    pub level: u8,
+    pub materials: Vec<MaterialName>,
+}
+
+impl Recipe {
+    /// Parse a recipe code, or a comma separated list of materials which uses the given invention level
+    pub fn parse(text: &str, invention_level: InventionLevel) -> Result<Recipe, String> {
+        if text.trim().to_lowercase().starts_with("ps") && text.contains(':') {
+            let code = RecipeCode::parse(text)?;
+            return match code.level {
+                InventionLevel::Single(level) if !code.materials.is_empty() => Ok(Recipe {
+                    level,
+                    materials: code.materials,
+                }),
+                InventionLevel::Single(_) => Err(String::from("The recipe code has no materials")),
+                InventionLevel::Range(..) => Err(String::from(
+                    "The recipe code has a level range instead of a single level",
+                )),
+            };
+        }
+
+        let level = match invention_level {
+            InventionLevel::Single(x) => x,
+            InventionLevel::Range(..) => {
+                return Err(String::from(
+                    "A recipe without a code needs a single invention level",
+                ))
+            }
+        };
+        let materials = text
+            .split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(|x| MaterialName::from_str(x).map_err(|err| format!("{err} '{}'", x.yellow())))
+            .collect::<Result<Vec<_>, _>>()?;
+        if materials.is_empty() {
+            return Err(String::from("The recipe has no materials"));
+        }
+        Ok(Recipe { level, materials })
+    }
+}
+
+/// Slots are counted from 1 in the order they are filled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edit {
+    Replace {
+        slot: usize,
+        from: MaterialName,
+        to: MaterialName,
+    },
+    Add {
+        slot: usize,
+        mat: MaterialName,
+    },
+    Remove {
+        slot: usize,
+        mat: MaterialName,
+    },
+    Swap(usize, usize),
+}
+
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Edit::Replace { slot, from, to } => write!(f, "Replace {from} in slot {slot} by {to}"),
+            Edit::Add { slot, mat } => write!(f, "Add {mat} in slot {slot}"),
+            Edit::Remove { slot, mat } => write!(f, "Remove {mat} from slot {slot}"),
+            Edit::Swap(x, y) => write!(f, "Swap slots {x} and {y}"),
+        }
+    }
+}
+
+/// Every recipe that differs by one edit, using the candidate materials for replacing and adding. Edits that result in
+/// a recipe that was already found are left out.
+pub fn one_edit_neighbours(
+    recipe: &[MaterialName],
+    candidates: &[MaterialName],
+    slot_count: usize,
+) -> Vec<(Edit, Vec<MaterialName>)> {
+    let mut neighbours: Vec<(Edit, Vec<MaterialName>)> = vec![];
+    let mut push = |edit, mats: Vec<MaterialName>| {
+        if mats != recipe && neighbours.iter().all(|x| x.1 != mats) {
+            neighbours.push((edit, mats));
+        }
+    };
+
+    for (i, from) in recipe.iter().enumerate() {
+        for to in candidates.iter().filter(|x| *x != from) {
+            let mut mats = recipe.to_vec();
+            mats[i] = *to;
+            push(
+                Edit::Replace {
+                    slot: i + 1,
+                    from: *from,
+                    to: *to,
+                },
+                mats,
+            );
+        }
+    }
+    if recipe.len() < slot_count {
+        for i in 0..=recipe.len() {
+            for mat in candidates {
+                let mut mats = recipe.to_vec();
+                mats.insert(i, *mat);
+                push(
+                    Edit::Add {
+                        slot: i + 1,
+                        mat: *mat,
+                    },
+                    mats,
+                );
+            }
+        }
+    }
+    if recipe.len() > 1 {
+        for (i, mat) in recipe.iter().enumerate() {
+            let mut mats = recipe.to_vec();
+            mats.remove(i);
+            push(
+                Edit::Remove {
+                    slot: i + 1,
+                    mat: *mat,
+                },
+                mats,
+            );
+        }
+    }
+    for (i, j) in (0..recipe.len()).tuple_combinations() {
+        let mut mats = recipe.to_vec();
+        mats.swap(i, j);
+        push(Edit::Swap(i + 1, j + 1), mats);
+    }
+
+    neighbours
+}
+
+pub struct Neighbour {
+    pub edit: Edit,
+    /// Probability 0 and an infinite price when the wanted gizmo can't be made
+    pub line: ResultLine,
+}
+
+pub struct NeighbourReport {
+    pub recipe: ResultLine,
+    /// Best first for the sort type
+    pub neighbours: Vec<Neighbour>,
+}
+
+impl NeighbourReport {
+    pub fn new(
+        recipe: ResultLine,
+        mut neighbours: Vec<Neighbour>,
+        sort_type: SortType,
+    ) -> NeighbourReport {
+        neighbours.sort_by(|x, y| {
+            f64::total_cmp(&y.line.sort_key(sort_type), &x.line.sort_key(sort_type))
+        });
+        NeighbourReport { recipe, neighbours }
+    }
+}
+
+impl fmt::Display for NeighbourReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", "Neighbours".bright_green().underline())?;
+        writeln!(
+            f,
+            " Recipe at level {}: {}",
+            self.recipe.level,
+            MaterialName::vec_to_string(&self.recipe.mat_combination)
+        )?;
+        writeln!(
+            f,
+            " Gizmo {}%, attempt {}%, price {}\n",
+            format_float(self.recipe.prob_gizmo),
+            format_float(self.recipe.prob_attempt),
+            format_price(self.recipe.price)
+        )?;
+
+        let width = self
+            .neighbours
+            .iter()
+            .map(|x| x.edit.to_string().chars().count())
+            .max()
+            .unwrap_or(0);
+        writeln!(
+            f,
+            " {:<width$}  {:>11}  {:>11}  {:>10}",
+            "Change", "Gizmo (%)", "Attempt (%)", "Price"
+        )?;
+        for neighbour in self.neighbours.iter() {
+            let line = &neighbour.line;
+            let delta = line.price - self.recipe.price;
+            let price = if !line.price.is_finite() {
+                String::from("impossible")
+            } else if delta > 0.0 {
+                format!("+{}", format_price(delta))
+            } else {
+                format_signed_price(delta)
+            };
+            writeln!(
+                f,
+                " {:<width$}  {:>+11.5}  {:>+11.5}  {:>10}",
+                neighbour.edit.to_string(),
+                (line.prob_gizmo - self.recipe.prob_gizmo) * 100.0,
+                (line.prob_attempt - self.recipe.prob_attempt) * 100.0,
+                price
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_edit_once() {
+        use MaterialName::*;
+        let recipe = [ArmadylComponents, ArmadylComponents, PreciseComponents];
+        let candidates = [ArmadylComponents, PreciseComponents, BladeParts];
+        let neighbours = one_edit_neighbours(&recipe, &candidates, 5);
+
+        // 3 slots with 2 other materials, 4 positions with 3 materials, 3 removals and 3 swaps, minus duplicates
+        let mats = neighbours.iter().map(|x| x.1.clone()).collect_vec();
+        assert!(mats.iter().all_unique());
+        assert!(!mats.contains(&recipe.to_vec()));
+        assert!(neighbours.contains(&(
+            Edit::Replace {
+                slot: 3,
+                from: PreciseComponents,
+                to: BladeParts
+            },
+            vec![ArmadylComponents, ArmadylComponents, BladeParts]
+        )));
+        assert!(neighbours.contains(&(
+            Edit::Add {
+                slot: 1,
+                mat: BladeParts
+            },
+            vec![
+                BladeParts,
+                ArmadylComponents,
+                ArmadylComponents,
+                PreciseComponents
+            ]
+        )));
+        assert!(neighbours.contains(&(
+            Edit::Remove {
+                slot: 1,
+                mat: ArmadylComponents
+            },
+            vec![ArmadylComponents, PreciseComponents]
+        )));
+        assert!(neighbours.contains(&(
+            Edit::Swap(1, 3),
+            vec![PreciseComponents, ArmadylComponents, ArmadylComponents]
+        )));
+        // Swapping the two Armadyl components changes nothing
+        assert!(!neighbours.iter().any(|x| x.0 == Edit::Swap(1, 2)));
+
+        let full = [BladeParts; 5];
+        assert!(one_edit_neighbours(&full, &candidates, 5)
+            .iter()
+            .all(|x| x.1.len() <= 5));
+        assert!(one_edit_neighbours(&[BladeParts], &[BladeParts], 5)
+            .iter()
+            .all(|x| !x.1.is_empty()));
+    }
+
+    #[test]
+    fn parse_recipes() {
+        use MaterialName::*;
+        assert_eq!(
+            Recipe::parse("armadyl, precise", InventionLevel::Single(120)),
+            Ok(Recipe {
+                level: 120,
+                materials: vec![ArmadylComponents, PreciseComponents]
+            })
+        );
+        assert_eq!(
+            Recipe::parse("ps1:w:99:precise3:45.36", InventionLevel::Range(1, 137)),
+            Ok(Recipe {
+                level: 99,
+                materials: vec![ArmadylComponents, PreciseComponents]
+            })
+        );
+        assert!(Recipe::parse("armadyl", InventionLevel::Range(1, 137)).is_err());
+        assert!(Recipe::parse("notamaterial", InventionLevel::Single(1)).is_err());
+        assert!(Recipe::parse("ps1:w:1-2::45", InventionLevel::Single(1)).is_err());
+    }
+}
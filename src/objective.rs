@@ -0,0 +1,386 @@
+use crate::{
+    prelude::*,
+    result::{distinct_material_count, rare_material_count},
+};
+use colored::Colorize;
+use itertools::Itertools;
+use std::{iter::Peekable, str::Chars};
+use strum::IntoEnumIterator;
+
+/// User defined expression to rank the results on, e.g. `price + 2e6 * distinct_rare` or
+/// `max: prob_gizmo / count(armadyl)`.
+///
+/// The expression is minimized unless it starts with `max:`. It supports numbers, `+ - * / ^`, parentheses and these
+/// values of a result:
+/// - `price`, `prob_gizmo`, `prob_attempt`, `level`
+/// - `slots`: amount of filled slots
+/// - `distinct`: amount of distinct materials
+/// - `rare`: amount of slots filled with rare materials
+/// - `distinct_rare`: amount of distinct rare materials
+/// - `value`: expected value per attempt of the gizmos given with `--gizmo-value`
+/// - `count(<material>)`: amount of slots filled with materials that contain this text, e.g. `count(armadyl)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Objective {
+    text: String,
+    maximize: bool,
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f64),
+    Variable(Variable),
+    Count(Vec<MaterialName>),
+    Negate(Box<Expr>),
+    Binary(Box<Expr>, Operator, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Variable {
+    Price,
+    ProbGizmo,
+    ProbAttempt,
+    Level,
+    Slots,
+    Distinct,
+    Rare,
+    DistinctRare,
+    Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+impl Objective {
+    pub fn parse(text: &str) -> Result<Objective, String> {
+        let trimmed = text.trim();
+        let (maximize, expr_text) = if let Some(x) = trimmed.strip_prefix("max:") {
+            (true, x)
+        } else if let Some(x) = trimmed.strip_prefix("min:") {
+            (false, x)
+        } else {
+            (false, trimmed)
+        };
+
+        let mut parser = Parser {
+            chars: expr_text.chars().peekable(),
+        };
+        let expr = parser.expression()?;
+        parser.skip_whitespace();
+        if let Some(c) = parser.chars.next() {
+            return Err(format!(
+                "Unexpected '{}' in objective '{}'",
+                c.to_string().yellow(),
+                text.yellow()
+            ));
+        }
+
+        Ok(Objective {
+            text: trimmed.to_string(),
+            maximize,
+            expr,
+        })
+    }
+
+    pub fn is_maximized(&self) -> bool {
+        self.maximize
+    }
+
+    /// Value of the expression for a result
+    pub fn evaluate(&self, line: &ResultLine) -> f64 {
+        self.expr.evaluate(line)
+    }
+
+    /// The value turned into a score where higher is always better
+    pub fn score(&self, line: &ResultLine) -> f64 {
+        let value = self.evaluate(line);
+        if value.is_nan() {
+            f64::NEG_INFINITY
+        } else if self.maximize {
+            value
+        } else {
+            -value
+        }
+    }
+}
+
+impl std::fmt::Display for Objective {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl serde::Serialize for Objective {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.text)
+    }
+}
+
+impl Expr {
+    fn evaluate(&self, line: &ResultLine) -> f64 {
+        match self {
+            Expr::Number(x) => *x,
+            Expr::Variable(var) => match var {
+                Variable::Price => line.price,
+                Variable::ProbGizmo => line.prob_gizmo,
+                Variable::ProbAttempt => line.prob_attempt,
+                Variable::Level => line.level as f64,
+                Variable::Slots => line.mat_combination.len() as f64,
+                Variable::Distinct => distinct_material_count(&line.mat_combination) as f64,
+                Variable::Rare => rare_material_count(&line.mat_combination) as f64,
+                Variable::DistinctRare => line
+                    .mat_combination
+                    .iter()
+                    .filter(|x| RARE_MATERIALS.contains(x))
+                    .unique()
+                    .count() as f64,
+                Variable::Value => line.value,
+            },
+            Expr::Count(mats) => line
+                .mat_combination
+                .iter()
+                .filter(|x| mats.contains(x))
+                .count() as f64,
+            Expr::Negate(x) => -x.evaluate(line),
+            Expr::Binary(x, op, y) => {
+                let (x, y) = (x.evaluate(line), y.evaluate(line));
+                match op {
+                    Operator::Add => x + y,
+                    Operator::Subtract => x - y,
+                    Operator::Multiply => x * y,
+                    Operator::Divide => x / y,
+                    Operator::Power => x.powf(y),
+                }
+            }
+        }
+    }
+}
+
+/// Recursive descent parser for the objective expressions
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|x| x.is_whitespace()).is_some() {}
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        self.chars.next_if_eq(&c).is_some()
+    }
+
+    /// expression = term (('+' | '-') term)*
+    fn expression(&mut self) -> Result<Expr, String> {
+        let mut expr = self.term()?;
+        loop {
+            let op = if self.eat('+') {
+                Operator::Add
+            } else if self.eat('-') {
+                Operator::Subtract
+            } else {
+                return Ok(expr);
+            };
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.term()?));
+        }
+    }
+
+    /// term = unary (('*' | '/') unary)*
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                Operator::Multiply
+            } else if self.eat('/') {
+                Operator::Divide
+            } else {
+                return Ok(expr);
+            };
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.unary()?));
+        }
+    }
+
+    /// unary = '-' unary | power
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat('-') {
+            Ok(Expr::Negate(Box::new(self.unary()?)))
+        } else {
+            self.power()
+        }
+    }
+
+    /// power = primary ('^' unary)?
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.primary()?;
+        if self.eat('^') {
+            Ok(Expr::Binary(
+                Box::new(base),
+                Operator::Power,
+                Box::new(self.unary()?),
+            ))
+        } else {
+            Ok(base)
+        }
+    }
+
+    /// primary = number | variable | 'count(' material ')' | '(' expression ')'
+    fn primary(&mut self) -> Result<Expr, String> {
+        self.skip_whitespace();
+        match self.chars.peek().copied() {
+            Some('(') => {
+                self.chars.next();
+                let expr = self.expression()?;
+                if !self.eat(')') {
+                    return Err(String::from("Missing ')' in objective"));
+                }
+                Ok(expr)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some(c) = self.chars.next_if(|x| x.is_alphanumeric() || *x == '_') {
+                    name.push(c);
+                }
+                self.identifier(&name)
+            }
+            Some(c) => Err(format!(
+                "Unexpected '{}' in objective",
+                c.to_string().yellow()
+            )),
+            None => Err(String::from("Objective ends unexpectedly")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Expr, String> {
+        let mut text = String::new();
+        while let Some(c) = self.chars.next_if(|x| x.is_ascii_digit() || *x == '.') {
+            text.push(c);
+        }
+        // Exponent, e.g. 2e6 or 1.5e-3
+        if let Some(e) = self.chars.next_if(|x| *x == 'e' || *x == 'E') {
+            text.push(e);
+            if let Some(sign) = self.chars.next_if(|x| *x == '-' || *x == '+') {
+                text.push(sign);
+            }
+            while let Some(c) = self.chars.next_if(|x| x.is_ascii_digit()) {
+                text.push(c);
+            }
+        }
+        text.parse()
+            .map(Expr::Number)
+            .map_err(|_| format!("Invalid number '{}' in objective", text.yellow()))
+    }
+
+    fn identifier(&mut self, name: &str) -> Result<Expr, String> {
+        let var = match name.to_lowercase().as_str() {
+            "price" => Variable::Price,
+            "prob_gizmo" => Variable::ProbGizmo,
+            "prob_attempt" => Variable::ProbAttempt,
+            "level" => Variable::Level,
+            "slots" => Variable::Slots,
+            "distinct" => Variable::Distinct,
+            "rare" => Variable::Rare,
+            "distinct_rare" => Variable::DistinctRare,
+            "value" => Variable::Value,
+            "count" => return self.count(),
+            _ => return Err(format!("Unknown value '{}' in objective", name.yellow())),
+        };
+        Ok(Expr::Variable(var))
+    }
+
+    fn count(&mut self) -> Result<Expr, String> {
+        if !self.eat('(') {
+            return Err(String::from("Expected '(' after count in objective"));
+        }
+        let mut filter = String::new();
+        while let Some(c) = self.chars.next_if(|x| *x != ')') {
+            filter.push(c);
+        }
+        if !self.eat(')') {
+            return Err(String::from("Missing ')' in objective"));
+        }
+
+        let filter = filter.trim().to_lowercase();
+        let mats = MaterialName::iter()
+            .filter(|x| !filter.is_empty() && x.to_string().to_lowercase().contains(&filter))
+            .collect_vec();
+        if mats.is_empty() {
+            return Err(format!(
+                "'{}' in objective does not match with any material",
+                filter.yellow()
+            ));
+        }
+        Ok(Expr::Count(mats))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn line() -> ResultLine {
+        use MaterialName::*;
+        ResultLine {
+            level: 120,
+            prob_gizmo: 0.5,
+            prob_attempt: 0.25,
+            price: 1000.0,
+            mat_combination: Arc::new(vec![
+                ArmadylComponents,
+                ArmadylComponents,
+                PreciseComponents,
+                BandosComponents,
+            ]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn evaluates_expressions() {
+        let eval = |text: &str| Objective::parse(text).unwrap().evaluate(&line());
+        assert_eq!(eval("price + 2e6 * distinct_rare"), 1000.0 + 2e6 * 2.0);
+        assert_eq!(eval("prob_gizmo / count(armadyl)"), 0.25);
+        assert_eq!(eval("(1 + 2) * 3 - -1"), 10.0);
+        assert_eq!(eval("2 ^ 3 ^ 2"), 512.0);
+        assert_eq!(eval("-2 ^ 2"), -4.0);
+        approx::assert_relative_eq!(eval("1.5e-3 * level"), 0.18);
+        assert_eq!(eval("slots + distinct + rare"), 4.0 + 3.0 + 3.0);
+        assert_eq!(eval("count(precise components)"), 1.0);
+    }
+
+    #[test]
+    fn direction_prefix() {
+        let min = Objective::parse("price").unwrap();
+        let max = Objective::parse("max: prob_gizmo").unwrap();
+        assert!(!min.is_maximized());
+        assert!(max.is_maximized());
+        assert_eq!(min.score(&line()), -1000.0);
+        assert_eq!(max.score(&line()), 0.5);
+        assert_eq!(
+            Objective::parse("min: price").unwrap().score(&line()),
+            -1000.0
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        assert!(Objective::parse("").is_err());
+        assert!(Objective::parse("price +").is_err());
+        assert!(Objective::parse("(price").is_err());
+        assert!(Objective::parse("price price").is_err());
+        assert!(Objective::parse("cost").is_err());
+        assert!(Objective::parse("count(notamaterial)").is_err());
+        assert!(Objective::parse("price % 2").is_err());
+    }
+}
@@ -0,0 +1,225 @@
+//! Best and worst slot order of a fixed set of materials
+use crate::{blockers::gizmo_text, calc_gizmo_probabilities, prelude::*, result::format_float};
+use colored::Colorize;
+use itertools::Itertools;
+use std::fmt;
+
+pub struct OrderResult {
+    /// Materials in the order they fill the gizmo
+    pub materials: Vec<MaterialName>,
+    pub prob_gizmo: f64,
+    pub prob_attempt: f64,
+}
+
+pub struct OrderReport {
+    pub wanted: Gizmo,
+    pub any_second: bool,
+    /// Every distinct order, highest probability per attempt first
+    pub orders: Vec<OrderResult>,
+}
+
+impl OrderReport {
+    pub fn best(&self) -> &OrderResult {
+        &self.orders[0]
+    }
+
+    pub fn worst(&self) -> &OrderResult {
+        self.orders.last().unwrap()
+    }
+}
+
+/// Every order of the materials that can give a different gizmo. Only the order in which the materials first occur
+/// matters, as explained at `calc_combination_count`, so each order is a permutation of the distinct materials followed
+/// by the repeated ones.
+pub fn distinct_orders(materials: &[MaterialName]) -> Vec<Vec<MaterialName>> {
+    let distinct = materials.iter().copied().unique().collect_vec();
+    let mut repeated = materials.to_vec();
+    for mat in distinct.iter() {
+        let i = repeated.iter().position(|x| x == mat).unwrap();
+        repeated.remove(i);
+    }
+
+    distinct
+        .iter()
+        .copied()
+        .permutations(distinct.len())
+        .map(|mut order| {
+            order.extend_from_slice(&repeated);
+            order
+        })
+        .collect()
+}
+
+/// Probability of the wanted gizmo for every distinct order of the materials
+pub fn optimise_order(
+    data: &Data,
+    budget: &Budget,
+    materials: &[MaterialName],
+    gizmo_type: GizmoType,
+    is_ancient: bool,
+    wanted: Gizmo,
+    any_second: bool,
+) -> OrderReport {
+    let orders = distinct_orders(materials)
+        .into_iter()
+        .map(|order| {
+            let gizmos = calc_gizmo_probabilities(data, budget, &order, gizmo_type, is_ancient);
+            let prob_attempt = gizmos
+                .iter()
+                .filter(|x| {
+                    if any_second {
+                        x.contains(&wanted)
+                    } else {
+                        x.same(&wanted)
+                    }
+                })
+                .fold(0.0, |acc, x| acc + x.probability);
+            let prob_empty = gizmos
+                .iter()
+                .filter(|x| x.perks.0.is_empty())
+                .fold(0.0, |acc, x| acc + x.probability);
+            let prob_gizmo = if prob_empty == 1.0 {
+                0.0
+            } else {
+                prob_attempt / (1.0 - prob_empty)
+            };
+            OrderResult {
+                materials: order,
+                prob_gizmo,
+                prob_attempt,
+            }
+        })
+        .sorted_by(|x, y| f64::total_cmp(&y.prob_attempt, &x.prob_attempt))
+        .collect();
+
+    OrderReport {
+        wanted,
+        any_second,
+        orders,
+    }
+}
+
+impl fmt::Display for OrderReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Probability of {} for {} distinct orders",
+            gizmo_text(&self.wanted, self.any_second).cyan(),
+            self.orders.len()
+        )?;
+        let (best, worst) = (self.best(), self.worst());
+        for (name, order) in [("Best", best), ("Worst", worst)] {
+            writeln!(
+                f,
+                " {:<6} gizmo {:>10}%, attempt {:>10}%: {}",
+                name,
+                format_float(order.prob_gizmo),
+                format_float(order.prob_attempt),
+                order.materials.iter().join(", ")
+            )?;
+        }
+        if best.prob_attempt - worst.prob_attempt < 1e-12 {
+            writeln!(f, "\nThe order doesn't change the probability.")
+        } else {
+            writeln!(
+                f,
+                " {:<6} gizmo {:>10}%, attempt {:>10}%",
+                "Gap",
+                format_float(best.prob_gizmo - worst.prob_gizmo),
+                format_float(best.prob_attempt - worst.prob_attempt)
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_of_first_occurrence() {
+        use MaterialName::*;
+        let materials = [
+            ArmadylComponents,
+            PreciseComponents,
+            ArmadylComponents,
+            ConnectorParts,
+        ];
+        let orders = distinct_orders(&materials);
+
+        assert_eq!(orders.len(), 6);
+        assert!(orders.iter().all_unique());
+        for order in orders.iter() {
+            assert_eq!(order.iter().counts(), materials.iter().counts());
+            assert_eq!(order[3], ArmadylComponents);
+        }
+        assert_eq!(distinct_orders(&[BladeParts; 3]), vec![vec![BladeParts; 3]]);
+    }
+
+    #[test]
+    fn best_order_first() {
+        use MaterialName::*;
+        let wanted = Gizmo {
+            perks: (
+                Perk {
+                    name: PerkName::Precise,
+                    rank: 3,
+                },
+                Perk::default(),
+            ),
+            ..Default::default()
+        };
+        let report = optimise_order(
+            &Data::load(),
+            &Budget::create(120, false),
+            &[
+                PreciseComponents,
+                ArmadylComponents,
+                ArmadylComponents,
+                ConnectorParts,
+            ],
+            GizmoType::Weapon,
+            false,
+            wanted,
+            false,
+        );
+
+        assert_eq!(report.orders.len(), 6);
+        assert!(report.best().prob_attempt >= report.worst().prob_attempt);
+        assert!(report
+            .orders
+            .iter()
+            .tuple_windows()
+            .all(|(x, y)| x.prob_attempt >= y.prob_attempt));
+        assert!(report.orders.iter().all(|x| x.prob_gizmo >= x.prob_attempt));
+
+        // Connector and flexible parts can generate perks with the same cost as mobile
+        let mobile = Gizmo {
+            perks: (
+                Perk {
+                    name: PerkName::Mobile,
+                    rank: 1,
+                },
+                Perk::default(),
+            ),
+            ..Default::default()
+        };
+        let report = optimise_order(
+            &Data::load(),
+            &Budget::create(99, false),
+            &[
+                ConnectorParts,
+                DextrousComponents,
+                ConnectorParts,
+                FlexibleParts,
+                FlexibleParts,
+            ],
+            GizmoType::Armour,
+            false,
+            mobile,
+            true,
+        );
+        assert!(report.best().prob_attempt - report.worst().prob_attempt > 0.5);
+        assert_eq!(report.best().materials[0], DextrousComponents);
+    }
+}
@@ -19,7 +19,7 @@ pub use gizmo::*;
 pub mod budget;
 pub use budget::*;
 
-use crate::component_prices::calc_gizmo_price;
+use crate::cost_model::CostModel;
 use colored::Colorize;
 use itertools::Itertools;
 use serde::Serialize;
@@ -49,7 +49,7 @@ impl Default for PerkRankValuesProbabilityContainer {
 
 // ---------------------------------------------------------------------------------------------------------------------
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct RankCombination {
     pub ranks: SmallVec<[PerkRankValues; 12]>,
     pub probability: f64,
@@ -83,7 +83,7 @@ pub type PartialPerkValuesVec = SmallVec<[PartialPerkValues; 10]>;
 
 // ---------------------------------------------------------------------------------------------------------------------
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SplitMaterials {
     pub conflict: Vec<MaterialName>,
     pub no_conflict: Vec<MaterialName>,
@@ -91,17 +91,7 @@ pub struct SplitMaterials {
 
 impl SplitMaterials {
     pub fn to_json(&self) -> String {
-        let conflict = self
-            .conflict
-            .iter()
-            .map(|x| format!("\"{}\"", x.to_string()))
-            .join(", ");
-        let no_conflict = self
-            .no_conflict
-            .iter()
-            .map(|x| format!("\"{}\"", x.to_string()))
-            .join(", ");
-        format!("{{\"conflict\": [{conflict}], \"no_conflict\": [{no_conflict}]}}")
+        serde_json::to_string(self).unwrap()
     }
 }
 
@@ -137,6 +127,10 @@ pub struct ResultLine {
     pub prob_attempt: f64,
     pub price: f64,
     pub mat_combination: Arc<Vec<MaterialName>>,
+    /// Expected value in coins per attempt of the gizmos given a value with --gizmo-value. Doesn't depend on the prices.
+    pub value: f64,
+    /// Score of the sort types that don't rank on a single field, where higher is better. Set by the result handler.
+    pub score: f64,
 }
 
 impl ResultLine {
@@ -145,19 +139,32 @@ impl ResultLine {
         prob_attempt: f64,
         prob_empty: f64,
         mat_combination: Arc<Vec<MaterialName>>,
+        cost_model: &dyn CostModel,
     ) -> ResultLine {
         let prob_gizmo = if prob_empty == 1.0 {
             0.0
         } else {
             prob_attempt / (1.0 - prob_empty)
         };
-        let price = calc_gizmo_price(&mat_combination, prob_gizmo);
+        let price = cost_model.gizmo_price(&mat_combination, prob_gizmo, prob_attempt);
         ResultLine {
             level,
             prob_gizmo,
             prob_attempt,
             price,
             mat_combination,
+            value: 0.0,
+            score: f64::NEG_INFINITY,
+        }
+    }
+
+    /// The value the sort type ranks on, higher is better
+    pub fn sort_key(&self, sort_type: SortType) -> f64 {
+        match sort_type {
+            SortType::Price => -self.price,
+            SortType::Gizmo => self.prob_gizmo,
+            SortType::Attempt => self.prob_attempt,
+            SortType::Custom | SortType::Budget | SortType::Value => self.score,
         }
     }
 
@@ -166,6 +173,7 @@ impl ResultLine {
             SortType::Price => (1.0 / self.price, 1.0 / other.price),
             SortType::Gizmo => (self.prob_gizmo, other.prob_gizmo),
             SortType::Attempt => (self.prob_attempt, other.prob_attempt),
+            SortType::Custom | SortType::Budget | SortType::Value => (self.score, other.score),
         };
         if x == y {
             if other.mat_combination.len() == self.mat_combination.len() {
@@ -184,6 +192,7 @@ impl ResultLine {
             SortType::Price => (1.0 / self.price, 1.0 / other.price),
             SortType::Gizmo => (self.prob_gizmo, other.prob_gizmo),
             SortType::Attempt => (self.prob_attempt, other.prob_attempt),
+            SortType::Custom | SortType::Budget | SortType::Value => (self.score, other.score),
         };
         (x == y)
             && (self.mat_combination.len() == other.mat_combination.len())
@@ -199,6 +208,8 @@ impl Default for ResultLine {
             prob_attempt: 0.0,
             price: f64::MAX,
             mat_combination: Arc::new(vec![]),
+            value: 0.0,
+            score: f64::NEG_INFINITY,
         }
     }
 }
@@ -1,30 +1,45 @@
-use crate::{utils::*, MaterialName, PerkName};
+use crate::{
+    component_prices::parse_price_override,
+    gizmo_values::{load_gizmo_values, GizmoValues},
+    neighbours::Recipe,
+    objective::Objective,
+    result::format_price,
+    utils::*,
+    MaterialName, PerkName,
+};
 use clap::{Parser, Subcommand, ValueEnum};
 use colored::*;
 use derive_more::Display;
 use itertools::Itertools;
+use serde::Serialize;
 use std::str::FromStr;
 use strum::IntoEnumIterator;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 pub struct Cli {
+    /// Gizmo type. Required unless given by the link or code of material-input, explain and blockers
     #[arg(value_enum, short('t'), long("type"))]
-    pub gizmo_type: GizmoType,
+    pub gizmo_type: Option<GizmoType>,
 
-    /// Invention level. Use two values separated by a comma to search in a range
+    /// Invention level. Use two values separated by a comma to search in a range. Required unless given by the link or
+    /// code of material-input, explain and blockers
     #[arg(
         short('l'),
         long("level"),
-        required(true),
         use_value_delimiter = true,
         value_delimiter = ','
     )]
     pub invention_level: Vec<u8>,
 
-    /// Is ancient gizmo
-    #[arg(short, long)]
-    pub ancient: bool,
+    /// Is ancient gizmo. Use '--ancient=false' for a normal gizmo when the link or code of material-input, explain and
+    /// blockers is for an ancient one. Overrides the link or code either way
+    #[arg(short, long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub ancient: Option<bool>,
+
+    /// Output format of the result
+    #[arg(value_enum, long, global = true, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
 
     /// Show the gizmo probabilities related to a given set of materials
     #[command(subcommand)]
@@ -34,6 +49,7 @@ pub struct Cli {
 // ---------------------------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Subcommand)]
+#[allow(clippy::large_enum_variant)]
 pub enum Commands {
     /// Find the optimal material combination of a given gizmo
     Gizmo {
@@ -64,6 +80,26 @@ pub enum Commands {
         #[arg(value_enum, short, long, default_value_t = SortType::Price)]
         sort_type: SortType,
 
+        /// Expression to rank the results on instead of the sort type, e.g. 'price + 2e6 * distinct_rare' or
+        /// 'max: prob_gizmo / count(armadyl)'. See the readme for the supported values
+        #[arg(long = "objective", value_name = "EXPR")]
+        objective: Option<String>,
+
+        /// Coins available to make the gizmo. Ranks the results on the probability to get the gizmo before the budget
+        /// runs out
+        #[arg(long = "budget", value_name = "COINS", conflicts_with = "objective")]
+        budget: Option<f64>,
+
+        /// Value in coins of a gizmo that is worth having, e.g. 'precise 4=2e6' or 'precise 5 + equilibrium 2=8e6'.
+        /// Ranks the results on the expected value per attempt minus the cost. Only material combinations that can make
+        /// the wanted gizmo are ranked, so give the wanted gizmo a value too. Can be used multiple times
+        #[arg(long = "gizmo-value", value_name = "GIZMO=COINS")]
+        gizmo_value: Vec<String>,
+
+        /// File with one gizmo value per line, in the same form as --gizmo-value
+        #[arg(long = "gizmo-value-file", value_name = "FILE")]
+        gizmo_value_file: Option<String>,
+
         /// Output file name. Set to false to disable output
         #[arg(long = "out-file", default_value_t = String::from("out.csv"))]
         out_file: String,
@@ -72,6 +108,91 @@ pub enum Commands {
         #[arg(long = "price-file", default_value_t = String::from("prices.txt"))]
         price_file: String,
 
+        /// Price override file name. Prices in this file take precedence over the ones from the wiki or the prices
+        /// file and the file is never written to. Set to false to disable
+        #[arg(long = "price-override-file", default_value_t = String::from("price_overrides.txt"))]
+        price_override_file: String,
+
+        /// Override the price of a material for this run only (e.g. 'armadyl=150000'). Can be used multiple times
+        #[arg(long = "price", value_name = "MATERIAL=PRICE")]
+        price: Vec<String>,
+
+        /// Refresh the prices file from the wiki when it is older than this many hours. Set to 0 to never refresh
+        #[arg(long = "price-max-age", value_name = "HOURS", default_value_t = 24)]
+        price_max_age: u64,
+
+        /// Where to fetch the component prices from
+        #[arg(value_enum, long = "price-provider", default_value_t = PriceProviderType::Wiki)]
+        price_provider: PriceProviderType,
+
+        /// Use a different endpoint for the price provider, e.g. a mirror of the API
+        #[arg(long = "price-endpoint", value_name = "URL")]
+        price_endpoint: Option<String>,
+
+        /// JSON table of items to disassemble. The cheapest effective cost of each material in it replaces the price
+        /// from the price provider when it is lower
+        #[arg(long = "disassembly-file", value_name = "FILE")]
+        disassembly_file: Option<String>,
+
+        /// Always use the disassembly price of the materials in the disassembly file, even when buying them is cheaper
+        #[arg(long = "prefer-disassembly", requires = "disassembly_file")]
+        prefer_disassembly: bool,
+
+        /// Shell recipe file name. Replaces the recipes of the gizmo shells listed in it, e.g. to use a fixed price for
+        /// bought shells or 0 for shells you already own. Set to false to disable
+        #[arg(long = "shell-recipe-file", default_value_t = String::from("shell_recipes.txt"))]
+        shell_recipe_file: String,
+
+        /// Refuse to solve when a material price is missing or can't be parsed, or when the prices are outdated
+        #[arg(long = "strict-prices")]
+        strict_prices: bool,
+
+        /// Re-price the best results with each material price changed by this percentage to see how robust the
+        /// ranking is, and show the price at which another result becomes cheaper
+        #[arg(long = "sensitivity", value_name = "PERCENT")]
+        sensitivity: Option<f64>,
+
+        /// Amount of Monte Carlo samples for the sensitivity analysis where all prices change at the same time
+        #[arg(
+            long = "monte-carlo",
+            value_name = "SAMPLES",
+            default_value_t = 0,
+            requires = "sensitivity"
+        )]
+        monte_carlo: u32,
+
+        /// Also show every combination for which no other combination is at least as good on price, both
+        /// probabilities, the amount of distinct materials and the amount of rare materials
+        #[arg(long = "pareto")]
+        pareto: bool,
+
+        /// Print a link to the perk calculator of the wiki after the best combination and each alt
+        #[arg(long = "calc-links")]
+        calc_links: bool,
+
+        /// For each material of the best combination, show the best combination without it and how much more it costs
+        #[arg(long = "exclusion-sweep")]
+        exclusion_sweep: bool,
+
+        /// Show why each conflict material is one, how much it grows the search and which exclusions shrink the search
+        /// the most for the least loss
+        #[arg(long = "conflicts")]
+        conflicts: bool,
+
+        /// Instead of solving, show how replacing, adding, removing or swapping one material changes a recipe. Takes a
+        /// recipe code or a comma separated list of materials in the order they fill the gizmo
+        #[arg(long = "neighbours", value_name = "RECIPE")]
+        neighbours: Option<String>,
+
+        /// Instead of solving, print the amount of combinations per amount of used slots and estimate how long the search
+        /// takes on this machine
+        #[arg(long = "dry-run")]
+        dry_run: bool,
+
+        /// List the materials to exclude so the dry run estimate is below this many minutes
+        #[arg(long = "time-target", value_name = "MINUTES", requires = "dry_run")]
+        time_target: Option<f64>,
+
         /// Amount of alternative combinations to show
         #[arg(long = "alt-count", short = 'A', default_value_t = 0, value_parser = clap::value_parser!(u8).range(..=254))]
         alt_count: u8,
@@ -82,17 +203,66 @@ pub enum Commands {
     },
     /// Show the gizmo probabilities for a given material combination
     MaterialInput {
-        /// Comma separated list of materials. Shorter names are accepted (e.g. 'precise' instead of 'Precise components')
-        #[arg(required(true), use_value_delimiter = true, value_delimiter = ',')]
-        mats: Vec<String>,
+        #[command(flatten)]
+        recipe: RecipeArgs,
+    },
+    /// Show every step of the calculation of the gizmo probabilities for a given material combination
+    Explain {
+        #[command(flatten)]
+        recipe: RecipeArgs,
+    },
+    /// Show how much probability of a gizmo is lost to each other perk of a given material combination
+    Blockers {
+        /// Wanted gizmo, e.g. 'precise 3' or 'precise 3 + biting 1'. The second perk doesn't matter when only one is
+        /// given, use '+ empty' for no second perk. Taken from the recipe code when not given.
+        #[arg(long, required_unless_present("code"))]
+        gizmo: Option<String>,
+
+        #[command(flatten)]
+        recipe: RecipeArgs,
+    },
+    /// Find the slot order of a given material combination that gives the highest probability of a gizmo
+    Order {
+        /// Wanted gizmo, e.g. 'precise 3' or 'precise 3 + biting 1'. The second perk doesn't matter when only one is
+        /// given, use '+ empty' for no second perk. Taken from the recipe code when not given.
+        #[arg(long, required_unless_present("code"))]
+        gizmo: Option<String>,
+
+        #[command(flatten)]
+        recipe: RecipeArgs,
     },
 }
 
 // ---------------------------------------------------------------------------------------------------------------------
 
+/// Material combination of the material-input, explain, blockers and order commands
+#[derive(Debug, clap::Args)]
+pub struct RecipeArgs {
+    /// Comma separated list of materials. Shorter names are accepted (e.g. 'precise' instead of 'Precise components')
+    #[arg(
+        required_unless_present_any(["link", "code"]),
+        use_value_delimiter = true,
+        value_delimiter = ','
+    )]
+    pub mats: Vec<String>,
+
+    /// Link to the perk calculator of the wiki to take the gizmo type, level, ancient and materials from. Given
+    /// options override the ones of the link
+    #[arg(long, conflicts_with("mats"))]
+    pub link: Option<String>,
+
+    /// Recipe code printed by the solver to take the gizmo type, level, ancient and materials from. Given options
+    /// override the ones of the code
+    #[arg(long, conflicts_with_all(["mats", "link"]))]
+    pub code: Option<String>,
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+
 /// Single letter aliases allowed
 #[repr(C)]
-#[derive(Debug, Display, Copy, Clone, PartialEq, Eq, ValueEnum)]
+#[derive(Debug, Display, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum GizmoType {
     #[value(alias("w"))]
     Weapon,
@@ -106,7 +276,8 @@ pub enum GizmoType {
 
 /// Single letter aliases allowed
 #[repr(C)]
-#[derive(Debug, Display, Copy, Clone, PartialEq, Eq, ValueEnum)]
+#[derive(Debug, Display, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SortType {
     #[value(alias("g"))]
     Gizmo,
@@ -114,11 +285,46 @@ pub enum SortType {
     Attempt,
     #[value(alias("p"))]
     Price,
+    /// Rank on the expression given with --objective
+    #[value(alias("c"))]
+    Custom,
+    /// Rank on the probability of success within the coins given with --budget
+    #[value(alias("b"))]
+    Budget,
+    /// Rank on the expected value per attempt of the gizmos given with --gizmo-value, minus the cost
+    #[value(alias("v"))]
+    Value,
 }
 
 // ---------------------------------------------------------------------------------------------------------------------
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Display, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceProviderType {
+    /// The component costs module of Runescape.wiki
+    Wiki,
+    /// The Grand Exchange prices API of Weird Gloop, with the wiki for the many components that can't be traded
+    Ge,
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Display, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Tables and text meant to be read in a terminal
+    Text,
+    /// A single JSON document, see the readme for the schema
+    Json,
+    /// Runescape wiki tables with Perk calclink templates
+    Wiki,
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+
+/// Serialized as a single level or as a `[low, high]` pair
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
 pub enum InventionLevel {
     Single(u8),
     Range(u8, u8),
@@ -126,7 +332,7 @@ pub enum InventionLevel {
 
 // ---------------------------------------------------------------------------------------------------------------------
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Args {
     pub invention_level: InventionLevel,
     pub gizmo_type: GizmoType,
@@ -138,10 +344,46 @@ pub struct Args {
     pub fuzzy: bool,
     pub exclude: Vec<MaterialName>,
     pub sort_type: SortType,
+    /// Used when the sort type is [`SortType::Custom`]
+    pub objective: Option<Objective>,
+    /// Used when the sort type is [`SortType::Budget`]
+    pub budget: Option<f64>,
+    /// Used when the sort type is [`SortType::Value`]
+    pub gizmo_values: GizmoValues,
     pub out_file: Option<String>,
     pub price_file: Option<String>,
+    pub price_override_file: Option<String>,
+    pub price_overrides: Vec<(MaterialName, f64)>,
+    pub price_max_age: Option<u64>,
+    pub price_provider: PriceProviderType,
+    pub price_endpoint: Option<String>,
+    pub disassembly_file: Option<String>,
+    pub prefer_disassembly: bool,
+    pub shell_recipe_file: Option<String>,
+    pub strict_prices: bool,
+    /// Relative price change for the sensitivity analysis, e.g. 0.3 for ±30%
+    pub sensitivity: Option<f64>,
+    pub monte_carlo_samples: u32,
+    /// Keep every result found so they can be ranked again with different prices
+    #[serde(skip)]
+    pub keep_pool: bool,
+    /// Keep the Pareto front of all results
+    pub pareto: bool,
+    /// Print wiki calculator links with the results
+    pub calc_links: bool,
+    /// Show the best combination without each material of the best combination. Needs the pool of all results.
+    pub exclusion_sweep: bool,
+    /// Show the conflict materials report. Needs the pool of all results for the suggested exclusions.
+    pub conflicts: bool,
+    /// Show the one material changes of this recipe instead of solving
+    pub neighbours: Option<Recipe>,
+    /// Estimate the size and runtime of the search instead of solving
+    pub dry_run: bool,
+    /// Runtime in seconds the dry run plans exclusions for
+    pub time_target: Option<f64>,
     pub result_depth: u8,
     pub limit_cpu: bool,
+    pub format: OutputFormat,
 }
 
 impl Args {
@@ -154,8 +396,30 @@ impl Args {
             fuzzy,
             exclude,
             sort_type,
+            objective,
+            budget,
+            gizmo_value,
+            gizmo_value_file,
             out_file,
             price_file,
+            price_override_file,
+            price,
+            price_max_age,
+            price_provider,
+            price_endpoint,
+            disassembly_file,
+            prefer_disassembly,
+            shell_recipe_file,
+            strict_prices,
+            sensitivity,
+            monte_carlo,
+            pareto,
+            calc_links,
+            exclusion_sweep,
+            conflicts,
+            neighbours,
+            dry_run,
+            time_target,
             alt_count,
             limit_cpu,
         } = &cli.command
@@ -218,6 +482,27 @@ impl Args {
                 }
             }).flatten().collect_vec();
 
+            let objective = objective.as_deref().map(Objective::parse).transpose()?;
+            if budget.is_some_and(|x| x.is_nan() || x <= 0.0) {
+                return Err(String::from("The budget must be more than 0 coins"));
+            }
+            let gizmo_values = load_gizmo_values(gizmo_value_file, gizmo_value)?;
+            let sort_type = match (&objective, budget, gizmo_values.is_empty(), sort_type) {
+                (Some(_), _, _, _) => SortType::Custom,
+                (None, Some(_), _, _) => SortType::Budget,
+                (None, None, false, _) => SortType::Value,
+                (None, None, true, SortType::Custom) => {
+                    return Err(String::from("Sort type custom needs an --objective"))
+                }
+                (None, None, true, SortType::Budget) => {
+                    return Err(String::from("Sort type budget needs a --budget"))
+                }
+                (None, None, true, SortType::Value) => {
+                    return Err(String::from("Sort type value needs a --gizmo-value"))
+                }
+                (None, None, true, x) => *x,
+            };
+
             let out_file = if out_file == "false" {
                 None
             } else {
@@ -230,21 +515,86 @@ impl Args {
                 Some(price_file.clone())
             };
 
+            let price_override_file = if price_override_file == "false" {
+                None
+            } else {
+                Some(price_override_file.clone())
+            };
+
+            let shell_recipe_file = if shell_recipe_file == "false" {
+                None
+            } else {
+                Some(shell_recipe_file.clone())
+            };
+
+            if neighbours.is_some() && cli.format != OutputFormat::Text {
+                return Err(format!(
+                    "--neighbours only works with --format {}",
+                    "text".yellow()
+                ));
+            }
+
+            if *dry_run && cli.format != OutputFormat::Text {
+                return Err(format!(
+                    "--dry-run only works with --format {}",
+                    "text".yellow()
+                ));
+            }
+
+            let price_overrides = price
+                .iter()
+                .map(|x| parse_price_override(x))
+                .collect::<Result<Vec<_>, _>>()?;
+
             Ok(Args {
                 invention_level,
-                gizmo_type: cli.gizmo_type,
-                ancient: cli.ancient,
+                gizmo_type: cli.gizmo_type.ok_or("Missing gizmo type")?,
+                ancient: cli.ancient.unwrap_or(false),
                 perk,
                 rank: *rank,
                 perk_two,
                 rank_two,
                 fuzzy,
-                sort_type: *sort_type,
+                sort_type,
+                objective,
+                budget: *budget,
+                gizmo_values,
                 exclude,
                 out_file,
                 price_file,
+                price_override_file,
+                price_overrides,
+                price_max_age: if *price_max_age == 0 {
+                    None
+                } else {
+                    Some(*price_max_age)
+                },
+                price_provider: *price_provider,
+                price_endpoint: price_endpoint.clone(),
+                disassembly_file: disassembly_file.clone(),
+                prefer_disassembly: *prefer_disassembly,
+                shell_recipe_file,
+                strict_prices: *strict_prices,
+                sensitivity: sensitivity.map(|x| x / 100.0),
+                monte_carlo_samples: *monte_carlo,
+                keep_pool: *exclusion_sweep || *conflicts,
+                pareto: *pareto,
+                calc_links: *calc_links,
+                exclusion_sweep: *exclusion_sweep,
+                conflicts: *conflicts,
+                neighbours: neighbours
+                    .as_ref()
+                    .map(|x| Recipe::parse(x, invention_level))
+                    .transpose()?,
+                dry_run: *dry_run,
+                time_target: match time_target {
+                    Some(x) if *x > 0.0 => Some(x * 60.0),
+                    Some(_) => return Err("The time target must be above 0 minutes".to_string()),
+                    None => None,
+                },
                 result_depth: *alt_count + 1,
                 limit_cpu: *limit_cpu,
+                format: cli.format,
             })
         } else {
             Err("Bad command".to_string())
@@ -265,10 +615,33 @@ impl Default for Args {
             fuzzy: false,
             exclude: vec![],
             sort_type: SortType::Price,
+            objective: None,
+            budget: None,
+            gizmo_values: GizmoValues::default(),
             out_file: Some(String::from("out.csv")),
             price_file: Some(String::from("prices.txt")),
+            price_override_file: Some(String::from("price_overrides.txt")),
+            price_overrides: vec![],
+            price_max_age: Some(24),
+            price_provider: PriceProviderType::Wiki,
+            price_endpoint: None,
+            disassembly_file: None,
+            prefer_disassembly: false,
+            shell_recipe_file: Some(String::from("shell_recipes.txt")),
+            strict_prices: false,
+            sensitivity: None,
+            monte_carlo_samples: 0,
+            keep_pool: false,
+            pareto: false,
+            calc_links: false,
+            exclusion_sweep: false,
+            conflicts: false,
+            neighbours: None,
+            dry_run: false,
+            time_target: None,
             result_depth: 1,
             limit_cpu: false,
+            format: OutputFormat::Text,
         }
     }
 }
@@ -316,9 +689,18 @@ impl std::fmt::Display for Args {
             }
         }
         let sort_type = match self.sort_type {
-            SortType::Attempt => "probability per attemp",
-            SortType::Gizmo => "probability per consumed gizmo",
-            SortType::Price => "estimated price",
+            SortType::Attempt => String::from("probability per attemp"),
+            SortType::Gizmo => String::from("probability per consumed gizmo"),
+            SortType::Price => String::from("estimated price"),
+            SortType::Custom => match &self.objective {
+                Some(objective) => format!("objective '{objective}'"),
+                None => String::from("objective"),
+            },
+            SortType::Budget => format!(
+                "probability of success within a budget of {}",
+                format_price(self.budget.unwrap_or_default())
+            ),
+            SortType::Value => String::from("expected value per attempt minus the cost"),
         };
         write!(f, " - Sort on {}", sort_type.cyan())?;
         if !self.exclude.is_empty() {
@@ -328,6 +710,20 @@ impl std::fmt::Display for Args {
                 self.exclude.iter().map(|x| x.to_string().cyan()).join(", ")
             )?;
         }
+        if !self.price_overrides.is_empty() {
+            write!(
+                f,
+                "\n - Price overrides: {}",
+                self.price_overrides
+                    .iter()
+                    .map(|(mat, price)| format!(
+                        "{} = {}",
+                        mat.to_string().cyan(),
+                        price.to_string().cyan()
+                    ))
+                    .join(", ")
+            )?;
+        }
         Ok(())
     }
 }
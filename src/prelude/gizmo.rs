@@ -76,6 +76,20 @@ impl PartialEq for Gizmo {
     }
 }
 
+/// Only the perks and the probability, the cost is an intermediate value of the calculation
+impl serde::Serialize for Gizmo {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("Gizmo", 2)?;
+        state.serialize_field("perks", &[self.perks.0, self.perks.1])?;
+        state.serialize_field("probability", &self.probability)?;
+        state.end()
+    }
+}
+
 impl std::fmt::Debug for Gizmo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Gizmo {{ perks: ({{ name: {}, rank: {} }}, {{ name: {}, rank: {} }}), cost: {}, probability: {} }}",
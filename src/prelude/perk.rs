@@ -1,6 +1,7 @@
 use crate::{PerkName, PerkRankValues};
+use serde::Serialize;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
 pub struct Perk {
     pub name: PerkName,
     pub rank: u8,
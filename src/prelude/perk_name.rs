@@ -131,3 +131,12 @@ impl From<PerkName> for usize {
         value as usize
     }
 }
+
+impl serde::Serialize for PerkName {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(&self)
+    }
+}
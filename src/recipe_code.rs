@@ -0,0 +1,284 @@
+//! Short codes to share a gizmo recipe, e.g. `ps1:wa:137:precise5+biting2:36.45.45.4`.
+//!
+//! The fields are separated by `:`
+//! - The version, `ps1`.
+//! - The gizmo type (`w`, `a` or `t`), followed by `a` for an ancient gizmo.
+//! - The invention level, or a range as `<low>-<high>`.
+//! - The wanted perks as name and rank without spaces, separated by `+`. Ends with `+any` when the second perk doesn't
+//!   matter. Empty when the code only holds materials.
+//! - The materials in the order they fill the gizmo, as the ids of the wiki calculator separated by `.`. Can be empty.
+use crate::{prelude::*, wiki};
+use colored::Colorize;
+use itertools::Itertools;
+use std::fmt;
+use strum::IntoEnumIterator;
+
+const VERSION: &str = "ps1";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecipeCode {
+    pub gizmo_type: GizmoType,
+    pub ancient: bool,
+    pub level: InventionLevel,
+    /// Both empty when there is no wanted gizmo, the second one is empty when there is no second perk
+    pub perks: (Perk, Perk),
+    /// The second perk doesn't matter
+    pub fuzzy: bool,
+    pub materials: Vec<MaterialName>,
+}
+
+impl RecipeCode {
+    /// Code of a result of the solver
+    pub fn from_result(args: &Args, line: &ResultLine) -> RecipeCode {
+        let perk_two = if args.perk_two == PerkName::Empty {
+            Perk::default()
+        } else {
+            Perk {
+                name: args.perk_two,
+                rank: args.rank_two,
+            }
+        };
+        RecipeCode {
+            gizmo_type: args.gizmo_type,
+            ancient: args.ancient,
+            level: InventionLevel::Single(line.level),
+            perks: (
+                Perk {
+                    name: args.perk,
+                    rank: args.rank,
+                },
+                perk_two,
+            ),
+            fuzzy: args.fuzzy,
+            materials: crate::result::gizmo_combination_sort(&line.mat_combination),
+        }
+    }
+
+    pub fn parse(code: &str) -> Result<RecipeCode, String> {
+        let code = code.trim().to_lowercase();
+        let fields = code.split(':').collect_vec();
+        if fields[0] != VERSION {
+            return Err(format!(
+                "'{}' is not a recipe code of this version of the solver",
+                code.yellow()
+            ));
+        }
+        if fields.len() != 5 {
+            return Err(format!(
+                "Recipe code '{}' does not have 5 fields",
+                code.yellow()
+            ));
+        }
+
+        let (gizmo_type, ancient) = match fields[1] {
+            "w" => (GizmoType::Weapon, false),
+            "a" => (GizmoType::Armour, false),
+            "t" => (GizmoType::Tool, false),
+            "wa" => (GizmoType::Weapon, true),
+            "aa" => (GizmoType::Armour, true),
+            "ta" => (GizmoType::Tool, true),
+            x => {
+                return Err(format!(
+                    "Unknown gizmo type '{}' in recipe code",
+                    x.yellow()
+                ))
+            }
+        };
+
+        let parse_level = |x: &str| {
+            x.parse::<u8>()
+                .ok()
+                .filter(|x| (1..=137).contains(x))
+                .ok_or(format!(
+                    "Invalid invention level '{}' in recipe code",
+                    x.yellow()
+                ))
+        };
+        let level = match fields[2].split_once('-') {
+            Some((low, high)) => {
+                let (low, high) = (parse_level(low)?, parse_level(high)?);
+                if low > high {
+                    return Err(format!(
+                        "Invalid invention level range '{}' in recipe code, the first level must be the lowest",
+                        fields[2].yellow()
+                    ));
+                }
+                InventionLevel::Range(low, high)
+            }
+            None => InventionLevel::Single(parse_level(fields[2])?),
+        };
+
+        let mut perks = fields[3].split('+').filter(|x| !x.is_empty()).collect_vec();
+        let fuzzy = perks.last() == Some(&"any");
+        if fuzzy {
+            perks.pop();
+        }
+        if perks.len() > 2 || (fuzzy && perks.is_empty()) {
+            return Err(format!(
+                "Invalid perks '{}' in recipe code",
+                fields[3].yellow()
+            ));
+        }
+        let perks = perks
+            .into_iter()
+            .map(parse_perk)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let materials = fields[4]
+            .split('.')
+            .filter(|x| !x.is_empty())
+            .map(|id| {
+                wiki::mat_from_link_id(id).ok_or(format!(
+                    "Unknown material id '{}' in recipe code",
+                    id.yellow()
+                ))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if (!ancient && materials.len() > 5) || materials.len() > 9 {
+            return Err("Recipe code has too many materials".to_string());
+        }
+
+        Ok(RecipeCode {
+            gizmo_type,
+            ancient,
+            level,
+            perks: (
+                perks.first().copied().unwrap_or_default(),
+                perks.get(1).copied().unwrap_or_default(),
+            ),
+            fuzzy,
+            materials,
+        })
+    }
+}
+
+impl fmt::Display for RecipeCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let gizmo_type = match self.gizmo_type {
+            GizmoType::Weapon => "w",
+            GizmoType::Armour => "a",
+            GizmoType::Tool => "t",
+        };
+        let level = match self.level {
+            InventionLevel::Single(x) => x.to_string(),
+            InventionLevel::Range(low, high) => format!("{low}-{high}"),
+        };
+        let mut perks = [self.perks.0, self.perks.1]
+            .into_iter()
+            .filter(|x| !x.is_empty())
+            .map(|x| format!("{}{}", perk_code(x.name), x.rank))
+            .collect_vec();
+        if self.fuzzy && !perks.is_empty() {
+            perks.push(String::from("any"));
+        }
+
+        write!(
+            f,
+            "{VERSION}:{gizmo_type}{}:{level}:{}:{}",
+            if self.ancient { "a" } else { "" },
+            perks.join("+"),
+            self.materials
+                .iter()
+                .map(|x| wiki::mat_link_id(*x))
+                .join(".")
+        )
+    }
+}
+
+/// Lowercase name without spaces or punctuation, e.g. `trophytakers`
+fn perk_code(perk: PerkName) -> String {
+    let name: &'static str = perk.into();
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|x| x.to_ascii_lowercase())
+        .collect()
+}
+
+fn parse_perk(text: &str) -> Result<Perk, String> {
+    let name = text.trim_end_matches(|x: char| x.is_ascii_digit());
+    let rank = text[name.len()..]
+        .parse()
+        .map_err(|_| format!("Perk '{}' in recipe code has no rank", text.yellow()))?;
+    let name = PerkName::iter()
+        .find(|x| *x != PerkName::Empty && perk_code(*x) == name)
+        .ok_or(format!("Unknown perk '{}' in recipe code", name.yellow()))?;
+    Ok(Perk { name, rank })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn round_trip() {
+        for code in [
+            "ps1:wa:137:precise5+biting2:36.45.45.4",
+            "ps1:t:1-120:trophytakers3+any:",
+            "ps1:a:50::1.1.2",
+            "ps1:ta:99:crystalshield4:77.77.77.77.77.77.77.77.77",
+        ] {
+            assert_eq!(RecipeCode::parse(code).unwrap().to_string(), code);
+        }
+        assert_eq!(
+            RecipeCode::parse(" PS1:WA:137:Precise5:36 ")
+                .unwrap()
+                .to_string(),
+            "ps1:wa:137:precise5:36"
+        );
+    }
+
+    #[test]
+    fn code_of_a_result() {
+        use MaterialName::*;
+        let args = Args {
+            gizmo_type: GizmoType::Weapon,
+            ancient: true,
+            perk: PerkName::Precise,
+            rank: 5,
+            perk_two: PerkName::Biting,
+            rank_two: 2,
+            ..Default::default()
+        };
+        let line = ResultLine {
+            level: 137,
+            mat_combination: Arc::new(vec![
+                PreciseComponents,
+                ArmadylComponents,
+                ConnectorParts,
+                ArmadylComponents,
+            ]),
+            ..Default::default()
+        };
+        let code = RecipeCode::from_result(&args, &line);
+        assert_eq!(code.to_string(), "ps1:wa:137:precise5+biting2:36.45.45.4");
+        assert_eq!(RecipeCode::parse(&code.to_string()), Ok(code));
+    }
+
+    #[test]
+    fn rejects_descending_level_ranges() {
+        assert!(RecipeCode::parse("ps1:w:120-90::1").is_err());
+        assert_eq!(
+            RecipeCode::parse("ps1:w:90-120::1").unwrap().level,
+            InventionLevel::Range(90, 120)
+        );
+        assert_eq!(
+            RecipeCode::parse("ps1:w:99-99::1").unwrap().level,
+            InventionLevel::Range(99, 99)
+        );
+    }
+
+    #[test]
+    fn rejects_bad_codes() {
+        assert!(RecipeCode::parse("ps2:w:1::1").is_err());
+        assert!(RecipeCode::parse("ps1:w:1:1").is_err());
+        assert!(RecipeCode::parse("ps1:x:1::1").is_err());
+        assert!(RecipeCode::parse("ps1:w:138::1").is_err());
+        assert!(RecipeCode::parse("ps1:w:1:precise::1").is_err());
+        assert!(RecipeCode::parse("ps1:w:1:notaperk1:1").is_err());
+        assert!(RecipeCode::parse("ps1:w:1:any:1").is_err());
+        assert!(RecipeCode::parse("ps1:w:1::82").is_err());
+        assert!(RecipeCode::parse("ps1:w:1::1.1.1.1.1.1").is_err());
+        assert!(RecipeCode::parse("ps1:wa:1::1.1.1.1.1.1").is_ok());
+    }
+}
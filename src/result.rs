@@ -1,4 +1,10 @@
-use crate::{prelude::*, utils::print_warning};
+use crate::{
+    cost_model::{success_within_budget, CostModel},
+    prelude::*,
+    recipe_code::RecipeCode,
+    utils::print_warning,
+    wiki::make_wiki_calc_link,
+};
 use colored::*;
 use itertools::Itertools;
 use std::{
@@ -8,16 +14,78 @@ use std::{
     thread::JoinHandle,
 };
 
-pub fn result_handler(
-    args: Arc<Args>,
-    rx: Receiver<Vec<ResultLine>>,
-) -> JoinHandle<Vec<Vec<ResultLine>>> {
+/// Everything the result handler collected during a solve
+pub struct SolverOutput {
+    /// The best lines per level, best first
+    pub best_per_level: Vec<Vec<ResultLine>>,
+    /// Only kept when [`Args::keep_pool`] is set
+    pub pool: Option<ResultPool>,
+    /// Only kept when [`Args::pareto`] is set
+    pub pareto_front: Option<ParetoFront>,
+}
+
+pub fn result_handler(args: Arc<Args>, rx: Receiver<Vec<ResultLine>>) -> JoinHandle<SolverOutput> {
     std::thread::spawn(move || {
-        let mut best_per_level = HashMap::new();
-        match args.invention_level {
-            InventionLevel::Single(x) => {
+        let mut best_per_level = empty_best_per_level(&args);
+        let mut pool = args.keep_pool.then(|| ResultPool::new(args.clone()));
+        let mut pareto_front = args.pareto.then(ParetoFront::default);
+
+        while let Ok(lines) = rx.recv() {
+            for mut line in lines.into_iter() {
+                score_line(&args, &mut line);
+                let current_bests = best_per_level.get_mut(&line.level).unwrap();
+                if let Some(pool) = pool.as_mut() {
+                    pool.insert(line.clone());
+                }
+                if let Some(front) = pareto_front.as_mut() {
+                    front.insert(&line);
+                }
+                insert_if_better(current_bests, line, args.sort_type);
+            }
+        }
+
+        SolverOutput {
+            best_per_level: finish_best_per_level(best_per_level),
+            pool,
+            pareto_front,
+        }
+    })
+}
+
+/// Set the score of a line for the sort types that rank on one
+pub fn score_line(args: &Args, line: &mut ResultLine) {
+    match (args.sort_type, &args.objective, args.budget) {
+        (SortType::Custom, Some(objective), _) => line.score = objective.score(line),
+        (SortType::Budget, _, Some(budget)) => {
+            // The price is the cost of one consumed gizmo divided by the probability per consumed gizmo
+            let attempt_cost = line.price * line.prob_gizmo;
+            line.score = success_within_budget(budget, attempt_cost, line.prob_gizmo);
+        }
+        // Shell and materials are only consumed when the gizmo is not empty, which costs the price times the
+        // probability per attempt on average
+        (SortType::Value, _, _) => line.score = line.value - line.price * line.prob_attempt,
+        _ => (),
+    }
+}
+
+fn empty_best_per_level(args: &Args) -> HashMap<u8, Vec<ResultLine>> {
+    let mut best_per_level = HashMap::new();
+    match args.invention_level {
+        InventionLevel::Single(x) => {
+            best_per_level.insert(
+                x,
+                vec![
+                    ResultLine {
+                        ..Default::default()
+                    };
+                    args.result_depth as usize
+                ],
+            );
+        }
+        InventionLevel::Range(x, y) => {
+            for lvl in (x..=y).step_by(2) {
                 best_per_level.insert(
-                    x,
+                    lvl,
                     vec![
                         ResultLine {
                             ..Default::default()
@@ -26,43 +94,198 @@ pub fn result_handler(
                     ],
                 );
             }
-            InventionLevel::Range(x, y) => {
-                for lvl in (x..=y).step_by(2) {
-                    best_per_level.insert(
-                        lvl,
-                        vec![
-                            ResultLine {
-                                ..Default::default()
-                            };
-                            args.result_depth as usize
-                        ],
-                    );
-                }
+        }
+    }
+    best_per_level
+}
+
+fn insert_if_better(current_bests: &mut Vec<ResultLine>, line: ResultLine, sort_type: SortType) {
+    if let Some((i, x)) = current_bests
+        .iter()
+        .find_position(|x| line.is_better(x, sort_type))
+    {
+        if !line.eq(x, sort_type) {
+            current_bests.pop();
+            current_bests.insert(i, line);
+        }
+    }
+}
+
+fn finish_best_per_level(best_per_level: HashMap<u8, Vec<ResultLine>>) -> Vec<Vec<ResultLine>> {
+    best_per_level
+        .into_values()
+        .sorted_by(|x, y| x[0].level.cmp(&y[0].level))
+        .filter(|x| x[0].prob_gizmo > 0.0)
+        .collect_vec()
+}
+
+/// Every material combination the solver found, without depending on the prices. Keeps the best order of the materials
+/// for each combination per level so the results can be ranked again with other prices without solving again.
+///
+/// Only kept when [`Args::keep_pool`] is set as it can hold millions of lines for large searches.
+pub struct ResultPool {
+    args: Arc<Args>,
+    lines: HashMap<u8, HashMap<Vec<MaterialName>, ResultLine>>,
+}
+
+impl ResultPool {
+    pub fn new(args: Arc<Args>) -> ResultPool {
+        ResultPool {
+            args,
+            lines: HashMap::new(),
+        }
+    }
+
+    pub fn args(&self) -> &Args {
+        &self.args
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.values().map(|x| x.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn insert(&mut self, line: ResultLine) {
+        let key = line.mat_combination.iter().copied().sorted().collect_vec();
+        let lines = self.lines.entry(line.level).or_default();
+        match lines.get(&key) {
+            Some(x) if !line.is_better(x, self.args.sort_type) => (),
+            _ => {
+                lines.insert(key, line);
             }
         }
+    }
 
-        while let Ok(lines) = rx.recv() {
-            for line in lines.into_iter() {
-                let current_bests = best_per_level.get_mut(&line.level).unwrap();
+    /// Price all lines with the cost model and pick the best ones per level, in the same shape as the result of
+    /// [`crate::Solver::run`]
+    pub fn rerank(&self, cost_model: &dyn CostModel) -> Vec<Vec<ResultLine>> {
+        let mut best_per_level = empty_best_per_level(&self.args);
+        for line in self.lines.values().flat_map(|x| x.values()) {
+            let mut line = line.clone();
+            line.price =
+                cost_model.gizmo_price(&line.mat_combination, line.prob_gizmo, line.prob_attempt);
+            score_line(&self.args, &mut line);
+            if let Some(current_bests) = best_per_level.get_mut(&line.level) {
+                insert_if_better(current_bests, line, self.args.sort_type);
+            }
+        }
+        finish_best_per_level(best_per_level)
+    }
 
-                if let Some((i, x)) = current_bests
-                    .iter()
-                    .find_position(|x| line.is_better(x, args.sort_type))
-                {
-                    if !line.eq(x, args.sort_type) {
-                        current_bests.pop();
-                        current_bests.insert(i, line);
-                    }
-                }
+    /// The best lines per level that don't use any of the materials, in the same shape as the result of
+    /// [`crate::Solver::run`]. The same as solving again with the materials excluded.
+    pub fn best_without(&self, excluded: &[MaterialName]) -> Vec<Vec<ResultLine>> {
+        let mut best_per_level = empty_best_per_level(&self.args);
+        for line in self
+            .lines
+            .values()
+            .flat_map(|x| x.values())
+            .filter(|x| !x.mat_combination.iter().any(|mat| excluded.contains(mat)))
+        {
+            if let Some(current_bests) = best_per_level.get_mut(&line.level) {
+                insert_if_better(current_bests, line.clone(), self.args.sort_type);
             }
         }
+        finish_best_per_level(best_per_level)
+    }
 
-        best_per_level
-            .into_values()
-            .sorted_by(|x, y| x[0].level.cmp(&y[0].level))
-            .filter(|x| x[0].prob_gizmo > 0.0)
-            .collect_vec()
-    })
+    /// Price all lines with the cost model and keep the Pareto-optimal ones
+    pub fn pareto_front(&self, cost_model: &dyn CostModel) -> ParetoFront {
+        let mut front = ParetoFront::default();
+        for line in self.lines.values().flat_map(|x| x.values()) {
+            let mut line = line.clone();
+            line.price =
+                cost_model.gizmo_price(&line.mat_combination, line.prob_gizmo, line.prob_attempt);
+            front.insert(&line);
+        }
+        front
+    }
+}
+
+/// The lines for which no other line is at least as good on every objective at once: a lower price, a higher
+/// probability per gizmo and per attempt, fewer distinct materials and fewer rare materials. Each line on the front is
+/// the best choice for some trade-off between these.
+#[derive(Debug, Clone, Default)]
+pub struct ParetoFront {
+    /// Objectives are computed once per kept line, the front is checked for every result of the solve
+    lines: Vec<(ParetoObjectives, ResultLine)>,
+}
+
+impl ParetoFront {
+    pub fn insert(&mut self, line: &ResultLine) {
+        if line.prob_gizmo <= 0.0 {
+            return;
+        }
+        let objectives = ParetoObjectives::new(line);
+        if self
+            .lines
+            .iter()
+            .any(|(x, _)| x.is_at_least_as_good(&objectives))
+        {
+            return;
+        }
+        self.lines
+            .retain(|(x, _)| !objectives.is_at_least_as_good(x));
+        self.lines.push((objectives, line.clone()));
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Lines on the front, cheapest first
+    pub fn lines(&self) -> Vec<&ResultLine> {
+        self.lines
+            .iter()
+            .map(|(_, line)| line)
+            .sorted_by(|x, y| x.price.total_cmp(&y.price))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ParetoObjectives {
+    price: f64,
+    prob_gizmo: f64,
+    prob_attempt: f64,
+    distinct_materials: usize,
+    rare_materials: usize,
+}
+
+impl ParetoObjectives {
+    fn new(line: &ResultLine) -> ParetoObjectives {
+        ParetoObjectives {
+            price: line.price,
+            prob_gizmo: line.prob_gizmo,
+            prob_attempt: line.prob_attempt,
+            distinct_materials: distinct_material_count(&line.mat_combination),
+            rare_materials: rare_material_count(&line.mat_combination),
+        }
+    }
+
+    fn is_at_least_as_good(&self, other: &Self) -> bool {
+        self.price <= other.price
+            && self.prob_gizmo >= other.prob_gizmo
+            && self.prob_attempt >= other.prob_attempt
+            && self.distinct_materials <= other.distinct_materials
+            && self.rare_materials <= other.rare_materials
+    }
+}
+
+pub fn distinct_material_count(mats: &[MaterialName]) -> usize {
+    mats.iter().unique().count()
+}
+
+/// Amount of slots filled with rare materials
+pub fn rare_material_count(mats: &[MaterialName]) -> usize {
+    mats.iter().filter(|x| RARE_MATERIALS.contains(x)).count()
 }
 
 pub fn format_float(num: f64) -> String {
@@ -112,22 +335,46 @@ pub fn print_result(best_per_level: &Vec<Vec<ResultLine>>, args: &Args) {
     if let Some((best_gizmo_index, best_attempt_index, best_price_index)) =
         get_best_of_each(best_per_level)
     {
-        let best_wanted_index = match args.sort_type {
-            SortType::Gizmo => best_gizmo_index,
-            SortType::Attempt => best_attempt_index,
-            SortType::Price => best_price_index,
-        };
+        let best_wanted_index = get_best_wanted_index(best_per_level, args.sort_type).unwrap();
 
         let best_wanted = &best_per_level[best_wanted_index][0];
         let best_gizmo_prob = best_per_level[best_gizmo_index][0].prob_gizmo;
         let best_attempt_prob = best_per_level[best_attempt_index][0].prob_attempt;
         let best_price = best_per_level[best_price_index][0].price;
 
-        println!("┌───────┬───────────────────────────┬───────────┐");
-        println!("│       │      Probability (%)      │           │");
-        println!("│ Level ├─────────────┬─────────────┤   Price   │");
-        println!("│       │    Gizmo    │   Attempt   │           │");
-        println!("├───────┼─────────────┼─────────────┼───────────┤");
+        // Extra column with the probability of success within the budget
+        let budget = args.sort_type == SortType::Budget;
+        let column = |text: &'static str| if budget { text } else { "" };
+
+        let best_score = best_per_level[best_wanted_index][0].score;
+        println!(
+            "┌───────┬───────────────────────────┬───────────{}",
+            if budget {
+                "┬─────────────┐"
+            } else {
+                "┐"
+            }
+        );
+        println!(
+            "│       │      Probability (%)      │           │{}",
+            column("   Within    │")
+        );
+        println!(
+            "│ Level ├─────────────┬─────────────┤   Price   │{}",
+            column("   budget    │")
+        );
+        println!(
+            "│       │    Gizmo    │   Attempt   │           │{}",
+            column("     (%)     │")
+        );
+        println!(
+            "├───────┼─────────────┼─────────────┼───────────{}",
+            if budget {
+                "┼─────────────┤"
+            } else {
+                "┤"
+            }
+        );
 
         for (i, line) in best_per_level.iter().enumerate() {
             let (r1, g1, b1) = get_color(line[0].prob_gizmo / best_gizmo_prob);
@@ -141,6 +388,13 @@ pub fn print_result(best_per_level: &Vec<Vec<ResultLine>>, args: &Args) {
                 format_float(line[0].prob_attempt).truecolor(r2, g2, b2),
                 format_price(line[0].price).truecolor(r3, g3, b3)
             );
+            if budget {
+                let (r4, g4, b4) = get_color(line[0].score / best_score);
+                print!(
+                    "  {:>9}  │",
+                    format_float(line[0].score).truecolor(r4, g4, b4)
+                );
+            }
 
             if i == best_wanted_index {
                 println!(" <====")
@@ -149,85 +403,140 @@ pub fn print_result(best_per_level: &Vec<Vec<ResultLine>>, args: &Args) {
             }
         }
 
-        println!("└───────┴─────────────┴─────────────┴───────────┘\n");
-
-        let val = match args.sort_type {
-            SortType::Price => format_price(best_per_level[best_wanted_index][0].price),
-            SortType::Gizmo => format!(
-                "{}%",
-                format_float(best_per_level[best_wanted_index][0].prob_gizmo)
-            ),
-            SortType::Attempt => format!(
-                "{}%",
-                format_float(best_per_level[best_wanted_index][0].prob_attempt)
-            ),
-        };
+        println!(
+            "└───────┴─────────────┴─────────────┴───────────{}\n",
+            if budget {
+                "┴─────────────┘"
+            } else {
+                "┘"
+            }
+        );
+
+        let val = format_sort_value(best_wanted, args);
         println!(
             "Best combination at level {}:\n {:<8}: {}",
             best_wanted.level,
             val,
             MaterialName::vec_to_string(best_wanted.mat_combination.as_ref())
         );
+        println!(" Code: {}", RecipeCode::from_result(args, best_wanted));
+        if args.calc_links {
+            println!(" {}", calc_link(best_wanted, args));
+        }
 
         if args.result_depth > 1 {
             println!("\nAlts:");
             for alt in find_best_alts(best_per_level, args) {
-                let val = match args.sort_type {
-                    SortType::Price => format_price(alt.price),
-                    SortType::Gizmo => format!("{}%", format_float(alt.prob_gizmo)),
-                    SortType::Attempt => format!("{}%", format_float(alt.prob_attempt)),
-                };
+                let val = format_sort_value(alt, args);
                 println!(
                     " {:<8} @lvl {}: {}",
                     val,
                     alt.level,
                     MaterialName::vec_to_string(alt.mat_combination.as_ref())
                 );
-            }
-        }
-
-        #[cfg(feature = "wiki-template")]
-        {
-            let counts = best_wanted.mat_combination.iter().counts();
-            let mut mats = vec![];
-            for mat in best_wanted.mat_combination.iter().unique() {
-                let s = mat
-                    .to_string()
-                    .replace(" parts", "")
-                    .replace(" components", "");
-                for _ in 0..counts[mat] {
-                    mats.push(s.clone());
+                if args.calc_links {
+                    println!("   {}", calc_link(alt, args));
                 }
             }
-            if mats.len() >= 3 {
-                mats.swap(0, 2);
-                mats.swap(0, 1);
-            }
-            let mats = mats.join("|");
-            let gizmo_type = args.gizmo_type.to_string().to_lowercase();
-            let gizmo_image = match args.ancient {
-                true => format!("Ancient {gizmo_type}"),
-                false => {
-                    let (x, y) = gizmo_type.split_at(1);
-                    format!("{}{y}", x.to_uppercase())
-                }
-            };
-            let (level, potion) = match best_wanted.level {
-                ..=120 => ((best_wanted.level / 2) * 2, "none"),
-                121..=123 => (120, "normal"),
-                124..=125 => (120, "super"),
-                126.. => (120, "extreme"),
-            };
-            println!("[[File:{gizmo_image} gizmo.png|link=]] {{{{Perk calclink|{mats}|gizmo={gizmo_type}|level={level}|potion={potion}|text='''Probability: {:.2}%'''}}}}", best_wanted.prob_gizmo * 100.0);
-            println!("{{{{Perk calclink|{mats}|gizmo={gizmo_type}|level={level}|potion={potion}|text={} {}, {} {}}}}} ({:.2}%)",
-                args.perk, args.rank, args.perk_two, args.rank_two, best_wanted.prob_gizmo * 100.0);
         }
     } else {
         println!("No material combination found that can produce this gizmo.");
     }
 }
 
-pub fn get_best_of_each(best_per_level: &Vec<Vec<ResultLine>>) -> Option<(usize, usize, usize)> {
+fn calc_link(line: &ResultLine, args: &Args) -> String {
+    make_wiki_calc_link(
+        &line.mat_combination,
+        args.ancient,
+        args.gizmo_type,
+        line.level,
+    )
+    .underline()
+    .to_string()
+}
+
+pub fn print_pareto_front(front: &ParetoFront) {
+    println!(
+        "{}",
+        "Pareto front (price, probability, distinct and rare materials)"
+            .underline()
+            .bright_green()
+    );
+    println!(
+        " {:>5}  {:>11}  {:>11}  {:>9}  {:>8}  {:>4}  Materials",
+        "Level", "Gizmo (%)", "Attempt (%)", "Price", "Distinct", "Rare"
+    );
+    for line in front.lines() {
+        println!(
+            " {:>5}  {:>11}  {:>11}  {:>9}  {:>8}  {:>4}  {}",
+            line.level,
+            format_float(line.prob_gizmo),
+            format_float(line.prob_attempt),
+            format_price(line.price),
+            distinct_material_count(&line.mat_combination),
+            rare_material_count(&line.mat_combination),
+            MaterialName::vec_to_string(&line.mat_combination)
+        );
+    }
+}
+
+/// The value a line is ranked on, formatted for display
+pub fn format_sort_value(line: &ResultLine, args: &Args) -> String {
+    match args.sort_type {
+        SortType::Price => format_price(line.price),
+        SortType::Gizmo => format!("{}%", format_float(line.prob_gizmo)),
+        SortType::Attempt => format!("{}%", format_float(line.prob_attempt)),
+        SortType::Custom => match &args.objective {
+            Some(objective) => format_number(objective.evaluate(line)),
+            None => format_number(line.score),
+        },
+        SortType::Budget => format!("{}%", format_float(line.score)),
+        SortType::Value => format!("{} per attempt", format_signed_price(line.score)),
+    }
+}
+
+pub fn format_signed_price(num: f64) -> String {
+    if num < 0.0 {
+        format!("-{}", format_price(-num))
+    } else {
+        format_price(num)
+    }
+}
+
+pub fn format_number(num: f64) -> String {
+    if num == 0.0 || (1e-3..1e7).contains(&num.abs()) {
+        format!("{:.3}", num)
+    } else {
+        format!("{:.3e}", num)
+    }
+}
+
+/// Index of the level with the best line for the sort type
+pub fn get_best_wanted_index(
+    best_per_level: &[Vec<ResultLine>],
+    sort_type: SortType,
+) -> Option<usize> {
+    let (best_gizmo_index, best_attempt_index, best_price_index) =
+        get_best_of_each(best_per_level)?;
+    Some(match sort_type {
+        SortType::Gizmo => best_gizmo_index,
+        SortType::Attempt => best_attempt_index,
+        SortType::Price => best_price_index,
+        SortType::Custom | SortType::Budget | SortType::Value => {
+            get_best_score_index(best_per_level)
+        }
+    })
+}
+
+/// Index of the level with the highest scoring line
+pub fn get_best_score_index(best_per_level: &[Vec<ResultLine>]) -> usize {
+    best_per_level
+        .iter()
+        .position_max_by(|x, y| x[0].score.total_cmp(&y[0].score))
+        .unwrap_or(0)
+}
+
+pub fn get_best_of_each(best_per_level: &[Vec<ResultLine>]) -> Option<(usize, usize, usize)> {
     let best_gizmo = best_per_level
         .iter()
         .position_max_by(|x, y| x[0].prob_gizmo.partial_cmp(&y[0].prob_gizmo).unwrap());
@@ -250,7 +559,7 @@ pub fn get_best_of_each(best_per_level: &Vec<Vec<ResultLine>>) -> Option<(usize,
 }
 
 pub fn find_best_alts<'a>(
-    best_per_level: &'a Vec<Vec<ResultLine>>,
+    best_per_level: &'a [Vec<ResultLine>],
     args: &Args,
 ) -> Vec<&'a ResultLine> {
     best_per_level
@@ -315,3 +624,58 @@ pub fn gizmo_combination_sort(v: &[MaterialName]) -> Vec<MaterialName> {
         .flatten()
         .collect_vec()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(price: f64, prob: f64, mats: &[MaterialName]) -> ResultLine {
+        ResultLine {
+            level: 1,
+            prob_gizmo: prob,
+            prob_attempt: prob,
+            price,
+            mat_combination: Arc::new(mats.to_vec()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn pareto_front_keeps_trade_offs() {
+        use MaterialName::*;
+        let mut front = ParetoFront::default();
+        front.insert(&line(100.0, 0.5, &[ArmadylComponents, PreciseComponents]));
+        // Cheaper but needs more distinct materials
+        front.insert(&line(
+            90.0,
+            0.5,
+            &[ArmadylComponents, PreciseComponents, BladeParts],
+        ));
+        // Beaten on everything by the first line
+        front.insert(&line(110.0, 0.4, &[ArmadylComponents, PreciseComponents]));
+        // Same as the first line
+        front.insert(&line(100.0, 0.5, &[PreciseComponents, ArmadylComponents]));
+
+        let lines = front.lines();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].price, 90.0);
+        assert_eq!(lines[1].price, 100.0);
+
+        // Better on every objective so it replaces both
+        front.insert(&line(80.0, 0.6, &[PreciseComponents]));
+        assert_eq!(front.len(), 1);
+    }
+
+    #[test]
+    fn counts_rare_materials_per_slot() {
+        use MaterialName::*;
+        let mats = [
+            ArmadylComponents,
+            ArmadylComponents,
+            PreciseComponents,
+            BladeParts,
+        ];
+        assert_eq!(distinct_material_count(&mats), 3);
+        assert_eq!(rare_material_count(&mats), 2);
+    }
+}
@@ -0,0 +1,379 @@
+use crate::{
+    component_prices::{
+        shells::{ShellCost, ShellRecipe},
+        PriceMap,
+    },
+    cost_model::{CostModel, DefaultCostModel},
+    prelude::*,
+    result::{format_price, gizmo_combination_sort},
+};
+use colored::Colorize;
+use itertools::Itertools;
+
+/// How the ranking of the top recipes holds up when the component prices change
+pub struct SensitivityReport {
+    /// The recipes that were compared, cheapest first at the current prices
+    pub candidates: Vec<ResultLine>,
+    /// Relative price change used for the per material analysis, e.g. 0.3 for ±30%
+    pub perturbation: f64,
+    pub materials: Vec<MaterialSensitivity>,
+    pub monte_carlo: Option<MonteCarloResult>,
+}
+
+pub struct MaterialSensitivity {
+    pub material: MaterialName,
+    pub price: f64,
+    /// Index of the cheapest candidate when the price of only this material goes up by the perturbation
+    pub best_when_up: usize,
+    /// Index of the cheapest candidate when the price of only this material goes down by the perturbation
+    pub best_when_down: usize,
+    /// Closest price at which the current best recipe stops being the cheapest
+    pub break_even: Option<BreakEven>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BreakEven {
+    pub price: f64,
+    /// Index of the candidate that becomes the cheapest past this price
+    pub overtaken_by: usize,
+}
+
+pub struct MonteCarloResult {
+    pub samples: u32,
+    /// How often each candidate was the cheapest
+    pub wins: Vec<u32>,
+}
+
+impl SensitivityReport {
+    /// Fraction of the single material perturbations after which the best recipe is still the cheapest
+    pub fn robustness(&self) -> f64 {
+        if self.materials.is_empty() {
+            return 1.0;
+        }
+        let kept = self
+            .materials
+            .iter()
+            .map(|x| (x.best_when_up == 0) as usize + (x.best_when_down == 0) as usize)
+            .sum::<usize>();
+        kept as f64 / (2 * self.materials.len()) as f64
+    }
+}
+
+/// Re-price the top results under changed prices. Only the price depends on the component prices so the probabilities of
+/// the results are reused as is. Returns `None` when there is nothing to compare.
+///
+/// - `perturbation`: relative change applied to one material at a time
+/// - `samples`: amount of Monte Carlo samples where every material price is scaled by a random factor within the
+///   perturbation at the same time. Set to 0 to skip.
+pub fn analyse(
+    best_per_level: &[Vec<ResultLine>],
+    prices: &PriceMap,
+    shell: &ShellRecipe,
+    perturbation: f64,
+    samples: u32,
+) -> Option<SensitivityReport> {
+    let candidates = best_per_level
+        .iter()
+        .flatten()
+        .filter(|x| x.prob_gizmo > 0.0)
+        .unique_by(|x| (x.level, gizmo_combination_sort(&x.mat_combination)))
+        .sorted_by(|x, y| x.price.total_cmp(&y.price))
+        .cloned()
+        .collect_vec();
+    if candidates.len() < 2 {
+        return None;
+    }
+
+    let materials = candidates
+        .iter()
+        .flat_map(|x| x.mat_combination.iter().copied())
+        .chain(shell_materials(shell))
+        .unique()
+        .sorted()
+        .collect_vec();
+
+    let materials = materials
+        .into_iter()
+        .map(|mat| {
+            let price = prices.get(mat);
+            let best_with = |factor: f64| {
+                let mut prices = prices.clone();
+                prices.set(mat, price * factor);
+                cheapest(&reprice(&candidates, &prices, shell))
+            };
+            MaterialSensitivity {
+                material: mat,
+                price,
+                best_when_up: best_with(1.0 + perturbation),
+                best_when_down: best_with((1.0 - perturbation).max(0.0)),
+                break_even: break_even(&candidates, prices, shell, mat),
+            }
+        })
+        .collect_vec();
+
+    let monte_carlo = if samples > 0 {
+        let mut rng = XorShift::new(0x9e3779b97f4a7c15);
+        let mut wins = vec![0; candidates.len()];
+        for _ in 0..samples {
+            let mut sample = prices.clone();
+            for mat in materials.iter().map(|x| x.material) {
+                let factor = 1.0 + perturbation * (2.0 * rng.next_f64() - 1.0);
+                sample.set(mat, prices.get(mat) * factor.max(0.0));
+            }
+            wins[cheapest(&reprice(&candidates, &sample, shell))] += 1;
+        }
+        Some(MonteCarloResult { samples, wins })
+    } else {
+        None
+    };
+
+    Some(SensitivityReport {
+        candidates,
+        perturbation,
+        materials,
+        monte_carlo,
+    })
+}
+
+fn shell_materials(shell: &ShellRecipe) -> Vec<MaterialName> {
+    match &shell.cost {
+        ShellCost::Recipe(recipe) => recipe.iter().map(|(_, mat)| *mat).collect(),
+        ShellCost::Fixed(_) => vec![],
+    }
+}
+
+fn reprice(candidates: &[ResultLine], prices: &PriceMap, shell: &ShellRecipe) -> Vec<f64> {
+    let cost_model = DefaultCostModel::new(prices.clone(), shell);
+    candidates
+        .iter()
+        .map(|x| cost_model.gizmo_price(&x.mat_combination, x.prob_gizmo, x.prob_attempt))
+        .collect()
+}
+
+fn cheapest(prices: &[f64]) -> usize {
+    prices
+        .iter()
+        .position_min_by(|x, y| x.total_cmp(y))
+        .unwrap()
+}
+
+/// The price of a recipe is linear in the price of each material, so the price at which another candidate becomes
+/// cheaper than the best one follows from the price of every candidate at two material prices.
+fn break_even(
+    candidates: &[ResultLine],
+    prices: &PriceMap,
+    shell: &ShellRecipe,
+    mat: MaterialName,
+) -> Option<BreakEven> {
+    let price = prices.get(mat);
+    let at = |x: f64| {
+        let mut prices = prices.clone();
+        prices.set(mat, x);
+        reprice(candidates, &prices, shell)
+    };
+    let base = at(0.0);
+    let slope = at(1.0)
+        .iter()
+        .zip(base.iter())
+        .map(|(x, y)| x - y)
+        .collect_vec();
+
+    (1..candidates.len())
+        .filter(|i| slope[*i] != slope[0])
+        .map(|i| BreakEven {
+            price: (base[i] - base[0]) / (slope[0] - slope[i]),
+            overtaken_by: i,
+        })
+        .filter(|x| x.price >= 0.0 && x.price.is_finite())
+        .min_by(|x, y| (x.price - price).abs().total_cmp(&(y.price - price).abs()))
+}
+
+/// Small xorshift64* generator so the Monte Carlo samples are reproducible without an extra dependency
+pub(crate) struct XorShift(u64);
+
+impl XorShift {
+    pub(crate) fn new(seed: u64) -> XorShift {
+        XorShift(seed.max(1))
+    }
+
+    /// Uniform in [0, 1)
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        let x = self.0.wrapping_mul(0x2545f4914f6cdd1d);
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl std::fmt::Display for SensitivityReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let percent = format!("{:.0}%", self.perturbation * 100.0);
+        writeln!(f, "{}", "Price sensitivity".underline().bright_green())?;
+        for (i, line) in self.candidates.iter().enumerate() {
+            writeln!(
+                f,
+                " #{:<2} Level {:>3}  {:>9}  {}",
+                i + 1,
+                line.level,
+                format_price(line.price),
+                MaterialName::vec_to_string(&line.mat_combination)
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(
+            f,
+            " {:<24} {:>9}  {:>6}  {:>6}  Break-even",
+            "Material",
+            "Price",
+            format!("+{percent}"),
+            format!("-{percent}")
+        )?;
+        for x in self.materials.iter() {
+            let best = |i: usize| {
+                let text = format!("{:>6}", format!("#{}", i + 1));
+                if i == 0 {
+                    text.normal()
+                } else {
+                    text.yellow()
+                }
+            };
+            let break_even = match x.break_even {
+                Some(b) => format!(
+                    "{} (#{} wins {})",
+                    format_price(b.price),
+                    b.overtaken_by + 1,
+                    if b.price > x.price { "above" } else { "below" }
+                ),
+                None => String::from("-"),
+            };
+            writeln!(
+                f,
+                " {:<24} {:>9}  {}  {}  {}",
+                x.material.to_string(),
+                format_price(x.price),
+                best(x.best_when_up),
+                best(x.best_when_down),
+                break_even
+            )?;
+        }
+        writeln!(f)?;
+        write!(
+            f,
+            " The best recipe stays the cheapest in {} of the single material changes of ±{percent}",
+            format!("{:.0}%", self.robustness() * 100.0).cyan()
+        )?;
+        if let Some(mc) = &self.monte_carlo {
+            write!(
+                f,
+                "\n and in {} of {} samples where all prices vary by up to ±{percent}",
+                format!("{:.1}%", mc.wins[0] as f64 / mc.samples as f64 * 100.0).cyan(),
+                mc.samples
+            )?;
+            for (i, wins) in mc.wins.iter().enumerate().skip(1).filter(|(_, x)| **x > 0) {
+                write!(
+                    f,
+                    "\n  - #{} is the cheapest in {:.1}% of the samples",
+                    i + 1,
+                    *wins as f64 / mc.samples as f64 * 100.0
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn line(prob_gizmo: f64, mats: &[MaterialName]) -> ResultLine {
+        ResultLine {
+            level: 1,
+            prob_gizmo,
+            prob_attempt: prob_gizmo,
+            price: 0.0,
+            mat_combination: Arc::new(mats.to_vec()),
+            ..Default::default()
+        }
+    }
+
+    fn free_shell() -> ShellRecipe {
+        ShellRecipe {
+            gizmo_type: GizmoType::Weapon,
+            ancient: false,
+            cost: ShellCost::Fixed(0.0),
+        }
+    }
+
+    /// Recipe 1 uses a cheap material but has a low probability, recipe 2 the other way around
+    fn setup() -> (Vec<Vec<ResultLine>>, PriceMap) {
+        let mut prices = PriceMap::new();
+        prices.set(MaterialName::PreciseComponents, 100.0);
+        prices.set(MaterialName::ArmadylComponents, 1000.0);
+        let shell = free_shell();
+        let mut lines = vec![
+            line(0.5, &[MaterialName::PreciseComponents]),
+            line(0.8, &[MaterialName::ArmadylComponents]),
+        ];
+        for x in lines.iter_mut() {
+            x.price = DefaultCostModel::new(prices.clone(), &shell).gizmo_price(
+                &x.mat_combination,
+                x.prob_gizmo,
+                x.prob_attempt,
+            );
+        }
+        (vec![lines], prices)
+    }
+
+    #[test]
+    fn finds_break_even_price() {
+        let (lines, prices) = setup();
+        let report = analyse(&lines, &prices, &free_shell(), 0.3, 0).unwrap();
+
+        // 100 / 0.5 = 200 vs 1000 / 0.8 = 1250
+        assert_eq!(report.candidates[0].price, 200.0);
+        let precise = report
+            .materials
+            .iter()
+            .find(|x| x.material == MaterialName::PreciseComponents)
+            .unwrap();
+        let b = precise.break_even.unwrap();
+        assert!((b.price - 625.0).abs() < 1e-9);
+        assert_eq!(b.overtaken_by, 1);
+
+        let armadyl = report
+            .materials
+            .iter()
+            .find(|x| x.material == MaterialName::ArmadylComponents)
+            .unwrap();
+        assert!((armadyl.break_even.unwrap().price - 160.0).abs() < 1e-9);
+        assert_eq!(report.robustness(), 1.0);
+    }
+
+    #[test]
+    fn large_changes_flip_the_ranking() {
+        let (lines, prices) = setup();
+        let report = analyse(&lines, &prices, &free_shell(), 6.0, 0).unwrap();
+        let precise = report
+            .materials
+            .iter()
+            .find(|x| x.material == MaterialName::PreciseComponents)
+            .unwrap();
+
+        assert_eq!(precise.best_when_up, 1);
+        assert_eq!(precise.best_when_down, 0);
+        assert!(report.robustness() < 1.0);
+    }
+
+    #[test]
+    fn monte_carlo_counts_every_sample() {
+        let (lines, prices) = setup();
+        let report = analyse(&lines, &prices, &free_shell(), 0.3, 500).unwrap();
+        let mc = report.monte_carlo.unwrap();
+
+        assert_eq!(mc.wins.iter().sum::<u32>(), 500);
+        assert_eq!(mc.wins[0], 500);
+    }
+}
@@ -0,0 +1,575 @@
+//! Links and markup for the Runescape wiki
+use crate::{
+    prelude::*,
+    result::{
+        find_best_alts, format_float, format_price, format_sort_value, get_best_wanted_index,
+        gizmo_combination_sort,
+    },
+};
+use colored::Colorize;
+use itertools::Itertools;
+use std::fmt::Write;
+use strum::IntoEnumIterator;
+
+/// Invention potions known by the `Perk calclink` template, with the amount of levels they boost
+const POTIONS: [(&str, u8); 3] = [("normal", 3), ("super", 5), ("extreme", 17)];
+
+/// Level and potion to give the `Perk calclink` template for an invention level, which can be boosted above 120. Only
+/// half the level matters for the budget, so a lower level with the same budget is used when a level can't be reached
+/// exactly.
+pub fn calc_level(level: u8) -> (u8, &'static str) {
+    if level <= 120 {
+        return (((level / 2) * 2).max(1), "none");
+    }
+    for (potion, boost) in POTIONS {
+        if let Some(base) = (1..=120u8).rev().find(|x| (x + boost) / 2 == level / 2) {
+            return (base, potion);
+        }
+    }
+    (120, POTIONS[POTIONS.len() - 1].0)
+}
+
+/// `Perk calclink` template of a material combination, showing the given text
+pub fn perk_calclink(
+    mat_combination: &[MaterialName],
+    gizmo_type: GizmoType,
+    level: u8,
+    text: &str,
+) -> String {
+    let counts = mat_combination.iter().counts();
+    let mut mats = vec![];
+    for mat in mat_combination.iter().unique() {
+        let s = mat
+            .to_string()
+            .replace(" parts", "")
+            .replace(" components", "");
+        for _ in 0..counts[mat] {
+            mats.push(s.clone());
+        }
+    }
+    // Adjust order to match the wiki
+    if mats.len() >= 3 {
+        mats.swap(0, 2);
+        mats.swap(0, 1);
+    }
+    let (level, potion) = calc_level(level);
+    format!(
+        "{{{{Perk calclink|{}|gizmo={}|level={level}|potion={potion}|text={text}}}}}",
+        mats.join("|"),
+        gizmo_type.to_string().to_lowercase()
+    )
+}
+
+fn gizmo_image(gizmo_type: GizmoType, ancient: bool) -> String {
+    if ancient {
+        format!(
+            "Ancient {} gizmo.png",
+            gizmo_type.to_string().to_lowercase()
+        )
+    } else {
+        format!("{gizmo_type} gizmo.png")
+    }
+}
+
+/// Materials without colours, e.g. `2 × Armadyl components, 1 × 5 Connector parts`
+fn materials_text(mat_combination: &[MaterialName]) -> String {
+    let counts = mat_combination.iter().counts();
+    mat_combination
+        .iter()
+        .unique()
+        .map(|mat| {
+            if COMMON_MATERIALS.contains(mat) {
+                format!("{} × 5 {mat}", counts[mat])
+            } else {
+                format!("{} × {mat}", counts[mat])
+            }
+        })
+        .join(", ")
+}
+
+/// The best combination, a table with the best combination of each level and a table with the alts
+pub fn result_markup(best_per_level: &[Vec<ResultLine>], args: &Args) -> String {
+    let mut out = String::new();
+    let best_wanted_index = match get_best_wanted_index(best_per_level, args.sort_type) {
+        Some(x) => x,
+        None => return String::from("No material combination found that can produce this gizmo."),
+    };
+    let best_wanted = &best_per_level[best_wanted_index][0];
+
+    writeln!(
+        out,
+        "[[File:{}|link=]] {}",
+        gizmo_image(args.gizmo_type, args.ancient),
+        perk_calclink(
+            &best_wanted.mat_combination,
+            args.gizmo_type,
+            best_wanted.level,
+            &format!(
+                "'''Probability: {}%'''",
+                format_float(best_wanted.prob_gizmo)
+            )
+        )
+    )
+    .unwrap();
+
+    writeln!(out, "\n{{| class=\"wikitable\"").unwrap();
+    writeln!(
+        out,
+        "! Level !! Probability per gizmo (%) !! Probability per attempt (%) !! Price !! Materials"
+    )
+    .unwrap();
+    for line in best_per_level
+        .iter()
+        .map(|x| &x[0])
+        .filter(|x| x.prob_gizmo > 0.0)
+    {
+        writeln!(out, "|-").unwrap();
+        writeln!(
+            out,
+            "| {}{} || {} || {} || {} || {}",
+            line.level,
+            if std::ptr::eq(line, best_wanted) {
+                " (best)"
+            } else {
+                ""
+            },
+            format_float(line.prob_gizmo),
+            format_float(line.prob_attempt),
+            format_price(line.price),
+            perk_calclink(
+                &line.mat_combination,
+                args.gizmo_type,
+                line.level,
+                &materials_text(&line.mat_combination)
+            )
+        )
+        .unwrap();
+    }
+    writeln!(out, "|}}").unwrap();
+
+    if args.result_depth > 1 {
+        let alts = find_best_alts(best_per_level, args);
+        if !alts.is_empty() {
+            writeln!(out, "\n{{| class=\"wikitable\"").unwrap();
+            writeln!(out, "! Alt !! Sort value !! Level !! Materials").unwrap();
+            for (i, alt) in alts.iter().enumerate() {
+                writeln!(out, "|-").unwrap();
+                writeln!(
+                    out,
+                    "| {} || {} || {} || {}",
+                    i + 1,
+                    format_sort_value(alt, args),
+                    alt.level,
+                    perk_calclink(
+                        &alt.mat_combination,
+                        args.gizmo_type,
+                        alt.level,
+                        &materials_text(&alt.mat_combination)
+                    )
+                )
+                .unwrap();
+            }
+            writeln!(out, "|}}").unwrap();
+        }
+    }
+
+    out.trim_end().to_string()
+}
+
+/// Table of all gizmos a material combination can make
+pub fn distribution_markup(
+    materials: &[MaterialName],
+    gizmos: &[Gizmo],
+    gizmo_type: GizmoType,
+    level: u8,
+) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "{}\n",
+        perk_calclink(materials, gizmo_type, level, &materials_text(materials))
+    )
+    .unwrap();
+    writeln!(out, "{{| class=\"wikitable sortable\"").unwrap();
+    writeln!(out, "! Perk one !! Perk two !! Probability (%)").unwrap();
+    for gizmo in gizmos {
+        let perk = |x: Perk| {
+            if x.is_empty() {
+                String::from("Empty")
+            } else {
+                format!("{} {}", x.name, x.rank)
+            }
+        };
+        writeln!(out, "|-").unwrap();
+        writeln!(
+            out,
+            "| {} || {} || {}",
+            perk(gizmo.perks.0),
+            perk(gizmo.perks.1),
+            format_float(gizmo.probability)
+        )
+        .unwrap();
+    }
+    write!(out, "|}}").unwrap();
+    out
+}
+
+/// Link to the perk calculator of the wiki with the materials filled in
+pub fn make_wiki_calc_link(
+    comb: &[MaterialName],
+    ancient: bool,
+    gizmo_type: GizmoType,
+    level: u8,
+) -> String {
+    let ancient = if ancient { "&a=1" } else { "" };
+    let gizmo_type = match gizmo_type {
+        GizmoType::Weapon => "g=1",
+        GizmoType::Armour => "g=2",
+        GizmoType::Tool => "g=3",
+    };
+
+    let mut comb = gizmo_combination_sort(comb)
+        .into_iter()
+        .map(mat_link_id)
+        .collect_vec();
+    comb.resize(9, "0");
+    comb.swap(0, 1); // Adjust order to match the wiki
+    comb.swap(1, 2);
+
+    format!(
+        "https://runescape.wiki/w/Calculator:Perks#{gizmo_type}&l={level}{ancient}&m={}",
+        comb.into_iter().join(",")
+    )
+}
+
+/// Gizmo recipe filled in by a link to the perk calculator
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalcLink {
+    pub gizmo_type: GizmoType,
+    pub level: u8,
+    pub ancient: bool,
+    /// In the slot order used by the solver, empty slots are left out
+    pub materials: Vec<MaterialName>,
+}
+
+/// Decode a link to the perk calculator, as made by [`make_wiki_calc_link`] or copied from the wiki. Only the part
+/// after the `#` is used.
+pub fn parse_wiki_calc_link(url: &str) -> Result<CalcLink, String> {
+    let (_, params) = url
+        .trim()
+        .split_once('#')
+        .ok_or(format!("'{}' is not a perk calculator link", url.yellow()))?;
+
+    let mut gizmo_type = None;
+    let mut level = None;
+    let mut ancient = false;
+    let mut ids = None;
+    for param in params.split('&').filter(|x| !x.is_empty()) {
+        let (key, value) = param.split_once('=').unwrap_or((param, ""));
+        match key {
+            "g" => {
+                gizmo_type = Some(match value {
+                    "1" => GizmoType::Weapon,
+                    "2" => GizmoType::Armour,
+                    "3" => GizmoType::Tool,
+                    _ => return Err(format!("Unknown gizmo type '{}' in link", value.yellow())),
+                })
+            }
+            "l" => {
+                level = Some(
+                    value
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|x| (1..=137).contains(x))
+                        .ok_or(format!(
+                            "Invalid invention level '{}' in link",
+                            value.yellow()
+                        ))?,
+                )
+            }
+            "a" => ancient = value == "1",
+            "m" => ids = Some(value.split(',').map(str::trim).collect_vec()),
+            _ => (),
+        }
+    }
+    let gizmo_type = gizmo_type.ok_or("Link has no gizmo type (g)")?;
+    let level = level.ok_or("Link has no invention level (l)")?;
+    let mut ids = ids.ok_or("Link has no materials (m)")?;
+
+    // Undo the reordering of make_wiki_calc_link
+    if ids.len() >= 3 {
+        ids.swap(1, 2);
+        ids.swap(0, 1);
+    }
+    let materials = ids
+        .into_iter()
+        .filter(|x| !x.is_empty() && *x != "0")
+        .map(|id| {
+            mat_from_link_id(id).ok_or(format!("Unknown material id '{}' in link", id.yellow()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if materials.is_empty() {
+        return Err("Link has no materials".to_string());
+    }
+    if (!ancient && materials.len() > 5) || materials.len() > 9 {
+        return Err("Link has too many materials".to_string());
+    }
+
+    Ok(CalcLink {
+        gizmo_type,
+        level,
+        ancient,
+        materials,
+    })
+}
+
+/// Id of a material in the `m` parameter of the calculator, 0 for an empty slot
+pub fn mat_link_id(mat: MaterialName) -> &'static str {
+    match mat {
+        MaterialName::BaseParts => "1",
+        MaterialName::BladeParts => "2",
+        MaterialName::ClearParts => "3",
+        MaterialName::ConnectorParts => "4",
+        MaterialName::CoverParts => "5",
+        MaterialName::CraftedParts => "6",
+        MaterialName::CrystalParts => "7",
+        MaterialName::DeflectingParts => "8",
+        MaterialName::DelicateParts => "9",
+        MaterialName::FlexibleParts => "10",
+        MaterialName::HeadParts => "11",
+        MaterialName::MagicParts => "12",
+        MaterialName::MetallicParts => "13",
+        MaterialName::OrganicParts => "14",
+        MaterialName::PaddedParts => "15",
+        MaterialName::PlatedParts => "16",
+        MaterialName::SimpleParts => "17",
+        MaterialName::SmoothParts => "18",
+        MaterialName::SpikedParts => "19",
+        MaterialName::SpiritualParts => "20",
+        MaterialName::StaveParts => "21",
+        MaterialName::TensileParts => "22",
+        MaterialName::DextrousComponents => "23",
+        MaterialName::DirectComponents => "24",
+        MaterialName::EnhancingComponents => "25",
+        MaterialName::EtherealComponents => "26",
+        MaterialName::EvasiveComponents => "27",
+        MaterialName::HealthyComponents => "28",
+        MaterialName::HeavyComponents => "29",
+        MaterialName::ImbuedComponents => "30",
+        MaterialName::LightComponents => "31",
+        MaterialName::LivingComponents => "32",
+        MaterialName::PiousComponents => "33",
+        MaterialName::PowerfulComponents => "34",
+        MaterialName::PreciousComponents => "35",
+        MaterialName::PreciseComponents => "36",
+        MaterialName::ProtectiveComponents => "37",
+        MaterialName::RefinedComponents => "38",
+        MaterialName::SharpComponents => "39",
+        MaterialName::StrongComponents => "40",
+        MaterialName::StunningComponents => "41",
+        MaterialName::SubtleComponents => "42",
+        MaterialName::SwiftComponents => "43",
+        MaterialName::VariableComponents => "44",
+        MaterialName::ArmadylComponents => "45",
+        MaterialName::AscendedComponents => "46",
+        MaterialName::AvernicComponents => "47",
+        MaterialName::BandosComponents => "48",
+        MaterialName::BrassicanComponents => "49",
+        MaterialName::ClockworkComponents => "50",
+        MaterialName::CorporealComponents => "51",
+        MaterialName::CulinaryComponents => "52",
+        MaterialName::CywirComponents => "53",
+        MaterialName::DragonfireComponents => "54",
+        MaterialName::ExplosiveComponents => "55",
+        MaterialName::FacetedComponents => "56",
+        MaterialName::FortunateComponents => "57",
+        MaterialName::FungalComponents => "58",
+        MaterialName::HarnessedComponents => "59",
+        MaterialName::IlujankanComponents => "60",
+        MaterialName::KnightlyComponents => "61",
+        MaterialName::NoxiousComponents => "62",
+        MaterialName::OceanicComponents => "63",
+        MaterialName::PestiferousComponents => "64",
+        MaterialName::ResilientComponents => "65",
+        MaterialName::RumblingComponents => "66",
+        MaterialName::SaradominComponents => "67",
+        MaterialName::SerenComponents => "68",
+        MaterialName::ShadowComponents => "69",
+        MaterialName::ShiftingComponents => "70",
+        MaterialName::SilentComponents => "71",
+        MaterialName::ThirdAgeComponents => "72",
+        MaterialName::UndeadComponents => "73",
+        MaterialName::ZamorakComponents => "74",
+        MaterialName::ZarosComponents => "75",
+        MaterialName::ClassicComponents => "76",
+        MaterialName::HistoricComponents => "77",
+        MaterialName::TimewornComponents => "78",
+        MaterialName::VintageComponents => "79",
+        MaterialName::OffcutComponents => "80",
+        MaterialName::ManufacturedComponents => "81",
+        _ => "0",
+    }
+}
+
+/// Material of an id of the `m` parameter of the calculator, `None` for an empty slot or an unknown id
+pub fn mat_from_link_id(id: &str) -> Option<MaterialName> {
+    MaterialName::iter().find(|x| mat_link_id(*x) == id && id != "0")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn every_material_has_its_own_link_id() {
+        let ids = MaterialName::iter()
+            .map(mat_link_id)
+            .filter(|x| *x != "0")
+            .map(|x| x.parse::<u8>().unwrap())
+            .sorted()
+            .collect_vec();
+        assert_eq!(ids, (1..=81).collect_vec());
+
+        assert_eq!(mat_link_id(MaterialName::BaseParts), "1");
+        assert_eq!(mat_link_id(MaterialName::TensileParts), "22");
+        assert_eq!(mat_link_id(MaterialName::DextrousComponents), "23");
+        assert_eq!(mat_link_id(MaterialName::VariableComponents), "44");
+        assert_eq!(mat_link_id(MaterialName::ArmadylComponents), "45");
+        assert_eq!(mat_link_id(MaterialName::ZarosComponents), "75");
+        assert_eq!(mat_link_id(MaterialName::ClassicComponents), "76");
+        assert_eq!(mat_link_id(MaterialName::ManufacturedComponents), "81");
+    }
+
+    #[test]
+    fn calc_link_parameters() {
+        use MaterialName::*;
+        let mats = [
+            PreciseComponents,
+            ArmadylComponents,
+            ArmadylComponents,
+            ConnectorParts,
+        ];
+        assert_eq!(
+            make_wiki_calc_link(&mats, false, GizmoType::Weapon, 120),
+            "https://runescape.wiki/w/Calculator:Perks#g=1&l=120&m=45,45,36,4,0,0,0,0,0"
+        );
+        assert_eq!(
+            make_wiki_calc_link(&mats, true, GizmoType::Armour, 137),
+            "https://runescape.wiki/w/Calculator:Perks#g=2&l=137&a=1&m=45,45,36,4,0,0,0,0,0"
+        );
+        assert_eq!(
+            make_wiki_calc_link(&[HistoricComponents; 9], true, GizmoType::Tool, 1),
+            "https://runescape.wiki/w/Calculator:Perks#g=3&l=1&a=1&m=77,77,77,77,77,77,77,77,77"
+        );
+    }
+
+    #[test]
+    fn calc_link_round_trip() {
+        use MaterialName::*;
+        let mats = [
+            PreciseComponents,
+            ArmadylComponents,
+            ArmadylComponents,
+            ConnectorParts,
+        ];
+        let link = parse_wiki_calc_link(&make_wiki_calc_link(&mats, true, GizmoType::Armour, 137));
+        assert_eq!(
+            link,
+            Ok(CalcLink {
+                gizmo_type: GizmoType::Armour,
+                level: 137,
+                ancient: true,
+                materials: gizmo_combination_sort(&mats),
+            })
+        );
+
+        for len in 1..=5 {
+            let mats = [BaseParts, ClearParts, ConnectorParts, CoverParts, HeadParts];
+            let url = make_wiki_calc_link(&mats[..len], false, GizmoType::Weapon, 1);
+            let link = parse_wiki_calc_link(&url).unwrap();
+            assert_eq!(
+                link.materials,
+                gizmo_combination_sort(&mats[..len]),
+                "{url}"
+            );
+        }
+
+        // Parameters in any order, copied without the domain
+        let link = parse_wiki_calc_link("Calculator:Perks#m=0,4,45&l=99&g=3").unwrap();
+        assert_eq!(link.gizmo_type, GizmoType::Tool);
+        assert_eq!(link.level, 99);
+        assert!(!link.ancient);
+        assert_eq!(link.materials, vec![ArmadylComponents, ConnectorParts]);
+    }
+
+    #[test]
+    fn rejects_bad_calc_links() {
+        assert!(parse_wiki_calc_link("https://runescape.wiki/w/Calculator:Perks").is_err());
+        assert!(parse_wiki_calc_link("#g=4&l=1&m=1").is_err());
+        assert!(parse_wiki_calc_link("#g=1&l=0&m=1").is_err());
+        assert!(parse_wiki_calc_link("#g=1&l=1&m=82").is_err());
+        assert!(parse_wiki_calc_link("#g=1&l=1&m=0,0,0").is_err());
+        assert!(parse_wiki_calc_link("#g=1&m=1").is_err());
+        assert!(parse_wiki_calc_link("#g=1&l=1&m=1,1,1,1,1,1").is_err());
+        assert!(parse_wiki_calc_link("#g=1&l=1&a=1&m=1,1,1,1,1,1").is_ok());
+    }
+
+    #[test]
+    fn boosted_levels_use_potions() {
+        assert_eq!(calc_level(1), (1, "none"));
+        assert_eq!(calc_level(97), (96, "none"));
+        assert_eq!(calc_level(120), (120, "none"));
+        assert_eq!(calc_level(121), (118, "normal"));
+        assert_eq!(calc_level(123), (120, "normal"));
+        assert_eq!(calc_level(124), (120, "super"));
+        assert_eq!(calc_level(125), (120, "super"));
+        assert_eq!(calc_level(126), (110, "extreme"));
+        assert_eq!(calc_level(137), (120, "extreme"));
+        // Every boosted level must end up with the same budget
+        for level in 121..=137u8 {
+            let (base, potion) = calc_level(level);
+            let boost = POTIONS.iter().find(|x| x.0 == potion).unwrap().1;
+            assert_eq!((base + boost) / 2, level / 2, "level {level}");
+        }
+    }
+
+    #[test]
+    fn calclink_template() {
+        use MaterialName::*;
+        let mats = [
+            ArmadylComponents,
+            ArmadylComponents,
+            PreciseComponents,
+            ConnectorParts,
+        ];
+        assert_eq!(
+            perk_calclink(&mats, GizmoType::Weapon, 125, "text"),
+            "{{Perk calclink|Armadyl|Precise|Armadyl|Connector|gizmo=weapon|level=120|potion=super|text=text}}"
+        );
+    }
+
+    #[test]
+    fn result_tables() {
+        let line = ResultLine {
+            level: 120,
+            prob_gizmo: 0.5,
+            prob_attempt: 0.25,
+            price: 1000.0,
+            mat_combination: Arc::new(vec![MaterialName::ArmadylComponents]),
+            ..Default::default()
+        };
+        let args = Args {
+            result_depth: 2,
+            ..Default::default()
+        };
+        let markup = result_markup(&[vec![line, ResultLine::default()]], &args);
+
+        assert!(markup.starts_with("[[File:Weapon gizmo.png|link=]] {{Perk calclink|Armadyl|"));
+        assert!(markup.contains(
+            "| 120 (best) || 50.00000 || 25.00000 || 1000 || {{Perk calclink|Armadyl|gizmo=weapon|level=120|potion=none|text=1 × Armadyl components}}"
+        ));
+        assert!(markup.ends_with("|}"));
+    }
+}